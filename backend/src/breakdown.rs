@@ -0,0 +1,139 @@
+use std::fmt::Display;
+
+use serde::{Serialize, Deserialize};
+use crate::{
+    damage::{DamageFormula, DamageSource, Multiplier, Skill, Type, Weapon, chrono2_multipliers, product},
+    enemy::EnemySecondaryStats,
+    player::StatLayers,
+};
+
+/// Every step of a single skill hit, so a number that looks wrong can be traced back.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DamageBreakdown {
+    pub formula: DamageFormula,
+    pub damage_type: Type,
+    pub source: DamageSource,
+    pub source_value: f32,
+    pub crit: bool,
+    /// Caster side: type modifiers, weapon boost, skill damage and stacking
+    pub multipliers: Vec<Multiplier>,
+    /// The hit as it leaves the caster, same as `Skill::compute`
+    pub value: f32,
+    /// Target side: the enemy's incoming modifiers, or the caster's Heal In for heals
+    pub incoming: Vec<Multiplier>,
+    pub final_value: f32,
+}
+
+impl Skill {
+    /// Breaks one hit down into its damage source and every multiplier on top of it.
+    /// Without an `enemy` there are no incoming modifiers (heals still get the caster's Heal In).
+    pub fn breakdown(&self, weapon: &Weapon, stats: &StatLayers, crit: bool, enemy: Option<&EnemySecondaryStats>) -> DamageBreakdown {
+        let crit = self.is_crit(crit);
+        let mut multipliers = match self.formula {
+            DamageFormula::Standard => self.damage_type.self_multipliers(stats, crit),
+            DamageFormula::Chrono2 => chrono2_multipliers(self, stats, crit),
+        };
+        multipliers.push(Multiplier::new("Weapon Boost", weapon.boost.multiplier()));
+        multipliers.push(Multiplier::new("Skill Damage", self.damage));
+        if self.properties.stacking.is_some() {
+            multipliers.push(Multiplier::new("Stacking", self.stacking_modifier()));
+        }
+
+        let incoming = match (self.damage_type, enemy) {
+            (Type::Heal, _) => vec![Multiplier::new("Heal In", stats.dynamic_stats.heal_in / 100.0)],
+            (damage_type, Some(enemy)) => enemy.incoming_multipliers(&damage_type),
+            (_, None) => vec![],
+        };
+
+        let value = self.compute(weapon, stats, crit);
+        DamageBreakdown {
+            formula: self.formula,
+            damage_type: self.damage_type,
+            source: self.dsrc,
+            source_value: self.dsrc.compute(weapon, self.damage_type.layer(stats)),
+            crit,
+            multipliers,
+            value,
+            final_value: value * product(&incoming),
+            incoming,
+        }
+    }
+}
+
+// Plain text version, used for tooltips
+impl Display for DamageBreakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:?} ({:?}): {:.1}", self.source, self.damage_type, self.source_value)?;
+        for m in &self.multipliers {
+            writeln!(f, "  x {} {:.3}", m.name, m.value)?;
+        }
+        let rounded = if self.formula == DamageFormula::Chrono2 { " (rounded)" } else { "" };
+        write!(f, "= {:.1}{}", self.value, rounded)?;
+        if !self.incoming.is_empty() {
+            writeln!(f)?;
+            for m in &self.incoming {
+                writeln!(f, "  x {} {:.3}", m.name, m.value)?;
+            }
+            write!(f, "= {:.1}", self.final_value)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{damage::{Properties, StackGain, StackingFormula, StackingFunction}, player::SecondaryStats};
+
+    fn stats() -> StatLayers {
+        let mut stats = SecondaryStats { attack_power: 900.0, spell_power: 700.0, ..Default::default() };
+        stats.modify_all_out(20.0, false);
+        stats.modify_mag_out(38.35, false);
+        StatLayers::from(stats)
+    }
+
+    fn rebuilt(b: &DamageBreakdown) -> f32 {
+        b.source_value * product(&b.multipliers)
+    }
+
+    #[test]
+    fn test_breakdown_matches_compute() {
+        let weapon = Weapon::default();
+        let stacking = StackingFunction::new(4, StackingFormula::Normal, StackGain::PerCast, None, false).with_stacks(2);
+        for damage_type in [Type::Physical, Type::Magical, Type::TrueDamage, Type::DamageOverTime, Type::Heal] {
+            for crit in [false, true] {
+                let skill = Skill {
+                    damage: 1.5,
+                    damage_type,
+                    properties: Properties { stacking: Some(stacking), ..Skill::default().properties },
+                    ..Default::default()
+                };
+                let b = skill.breakdown(&weapon, &stats(), crit, None);
+                assert_eq!(b.value, skill.compute(&weapon, &stats(), crit));
+                assert!((rebuilt(&b) - b.value).abs() < 1e-2, "{damage_type:?} crit {crit}");
+
+                let chrono = Skill { formula: DamageFormula::Chrono2, ..skill };
+                let b = chrono.breakdown(&weapon, &stats(), crit, None);
+                assert!((rebuilt(&b) - b.value).abs() <= 0.5, "Chrono2 {damage_type:?} crit {crit}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_breakdown_incoming() {
+        let weapon = Weapon::default();
+        let enemy = EnemySecondaryStats { all_in: 110.0, mag_in: 80.0, ..Default::default() };
+        let skill = Skill { damage_type: Type::Magical, dsrc: DamageSource::SP1, ..Default::default() };
+        let b = skill.breakdown(&weapon, &stats(), false, Some(&enemy));
+        let names: Vec<&str> = b.incoming.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["All In", "Mag In"]);
+        assert!((b.final_value - b.value * 1.1 * 0.8).abs() < 1e-2);
+        assert_eq!(enemy.incoming_modifier(&Type::TrueDamage), 1.0);
+
+        let json = serde_json::to_string(&b).unwrap();
+        let back: DamageBreakdown = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, b);
+        assert!(b.to_string().contains("Mag Out"));
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::{
+    damage::{Skill, Weapon, WeaponBoost},
+    enemy::{EnemyPhase, EnemySecondaryStats, EnemySkill},
+    error::BackendError,
+    gear::{Enhancement, EnhancementPattern, GearSlot, Trait, get_stats},
+    passive::{CustomPassive, OperationType, TargetType, apply_passives, derive_stats},
+    player::{Class, Player, PrimaryStats, SecondaryStats, StatLayers},
+    sim::Loadout,
+    stat::{StatDerivation, StatKey, capped},
+};
+
+/// A full character setup: class, gear, skills and the enemy it's tested against.
+/// This is what the app saves, keyed by name.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Build {
+    pub name: String,
+    pub level: Player,
+    pub equipment: Equipment,
+    pub weapon: Weapon,
+    pub class: Class,
+    pub primary_stats: PrimaryStats,
+    pub secondary_stats: SecondaryStats,
+    pub passives: Vec<CustomPassive>,
+    pub skills: Vec<(Skill, Vec<CustomPassive>, bool)>,
+    pub enemy: EnemySecondaryStats,
+    pub enemy_skills: Vec<(EnemySkill, Vec<CustomPassive>, bool)>,
+    /// Phases after the opening one, in the order they trigger
+    #[serde(default)]
+    pub enemy_phases: Vec<EnemyPhase>,
+    /// Save format, see `Build::VERSION`. Saves from before it existed load as 0.
+    #[serde(default)]
+    pub version: u32,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Equipment {
+    pub helm: Enhancement,
+    pub cape: Enhancement,
+    pub weapon: Enhancement,
+    pub class: Enhancement,
+}
+
+impl Equipment {
+    /// Every slot, in the order the pickers show them
+    pub const SLOTS: [GearSlot; 4] = [GearSlot::Helm, GearSlot::Cape, GearSlot::Weapon, GearSlot::Armor];
+
+    /// The enhancement in `slot`, the class slot counts as the armor
+    pub fn slot(&self, slot: GearSlot) -> &Enhancement {
+        match slot {
+            GearSlot::Helm => &self.helm,
+            GearSlot::Cape => &self.cape,
+            GearSlot::Weapon => &self.weapon,
+            GearSlot::Armor => &self.class,
+        }
+    }
+
+    pub fn slot_mut(&mut self, slot: GearSlot) -> &mut Enhancement {
+        match slot {
+            GearSlot::Helm => &mut self.helm,
+            GearSlot::Cape => &mut self.cape,
+            GearSlot::Weapon => &mut self.weapon,
+            GearSlot::Armor => &mut self.class,
+        }
+    }
+
+    /// The passives every slot's trait adds
+    pub fn trait_passives(&self) -> Vec<CustomPassive> {
+        Self::SLOTS.iter().flat_map(|&slot| self.slot(slot).r#trait.passives()).collect()
+    }
+
+    pub fn total_stats(&self) -> PrimaryStats {
+        let mut total = get_stats(&self.helm, GearSlot::Helm);
+        total.add(&get_stats(&self.cape, GearSlot::Cape));
+        total.add(&get_stats(&self.weapon, GearSlot::Weapon));
+        total.add(&get_stats(&self.class, GearSlot::Armor));
+
+        total
+    }
+}
+
+impl Default for Equipment {
+    fn default() -> Self {
+        Equipment {
+            helm:  Enhancement { level: 100, rarity: 6, pattern: EnhancementPattern::Anima, r#trait: Trait::None },
+            cape: Enhancement { level: 100, rarity: 6, pattern: EnhancementPattern::Forge, r#trait: Trait::None },
+            weapon: Enhancement { level: 100, rarity: 6, pattern: EnhancementPattern::Forge, r#trait: Trait::None },
+            class: Enhancement { level: 100, rarity: 5, pattern: EnhancementPattern::Lucky, r#trait: Trait::None },
+        }
+    }
+}
+
+impl Default for Build {
+    fn default() -> Self {
+        let player = Player::default();
+        let equipment = Equipment::default();
+        let class = Class::default();
+        let mut primary_stats = class.class_model.level_primary_stat_total(&player);
+        let equip_stats = equipment.total_stats();
+        primary_stats.add(&equip_stats);
+
+        Build {
+            name: "Archfishy".into(),
+            level: player.clone(),
+            equipment: equipment.clone(),
+            weapon: Weapon { range: 1.0, dps: 85.0, boost: WeaponBoost::Boost51x50 },
+            class: class.clone(),
+            primary_stats: primary_stats.clone(),
+            secondary_stats: class.class_model.secondary_stats_convert(&player, &primary_stats),
+            passives: vec![],
+            skills: vec![(Skill::default(), vec![], false); 5],
+            enemy: EnemySecondaryStats::enemy_default(),
+            enemy_skills: vec![(EnemySkill::default(), vec![], false); 1],
+            enemy_phases: vec![],
+            version: Build::VERSION,
+        }
+    }
+
+}
+
+impl Build {
+    /// Current save format.
+    /// 1: flat Heal In passives on the player raise `heal_in`, version 0 subtracted them.
+    pub const VERSION: u32 = 1;
+
+    /// Level and gear, before any passive
+    pub fn base_primary(&self) -> PrimaryStats {
+        let mut primary_stats = self.class.class_model.level_primary_stat_total(&self.level);
+        primary_stats.add(&self.equipment.total_stats());
+        primary_stats
+    }
+
+    fn permanent_passives(&self) -> impl Iterator<Item = &CustomPassive> + Clone {
+        // Timed passives belong to the dynamic layer, so only permanent ones count here
+        self.passives.iter().filter(|p| p.duration.is_none())
+    }
+
+    /// Puts on other gear, trading the old traits' passives for the new ones.
+    pub fn set_equipment(&mut self, equipment: Equipment) {
+        for passive in self.equipment.trait_passives() {
+            if let Some(i) = self.passives.iter().position(|p| *p == passive) {
+                self.passives.remove(i);
+            }
+        }
+        self.passives.extend(equipment.trait_passives());
+        self.equipment = equipment;
+        self.refresh_stats();
+    }
+
+    pub fn refresh_stats(&mut self) {
+        self.primary_stats = apply_passives(&self.base_primary(), TargetType::Primary, self.permanent_passives());
+        let secondary_stats = self.class.class_model.secondary_stats_convert(&self.level, &self.primary_stats);
+        self.secondary_stats = apply_passives(&secondary_stats, TargetType::Secondary, self.permanent_passives());
+    }
+
+    /// How each primary and secondary stat got its value after `refresh_stats`.
+    pub fn derivations(&self) -> (Vec<StatDerivation>, Vec<StatDerivation>) {
+        let primary = derive_stats(&self.base_primary(), TargetType::Primary, self.permanent_passives());
+        let secondary_stats = self.class.class_model.secondary_stats_convert(&self.level, &self.primary_stats);
+        let secondary = derive_stats(&secondary_stats, TargetType::Secondary, self.permanent_passives());
+        (primary, secondary)
+    }
+
+    /// Builds the static layer from level, gear and permanent passives,
+    /// and the dynamic layer with the given timed buffs on top of it.
+    /// Both are capped, unlike `secondary_stats`, and both start from the base
+    /// primary stats so a stale `primary_stats` can't pull them apart.
+    pub fn stat_layers(&self, buffs: &[CustomPassive]) -> StatLayers {
+        let static_stats = capped(&self.secondary_with(self.permanent_passives()));
+        if buffs.is_empty() {
+            return StatLayers::from(static_stats);
+        }
+
+        // Buffs share layers with the permanent passives rather than stacking on their result
+        let dynamic_stats = self.secondary_with(self.permanent_passives().chain(buffs));
+        StatLayers::new(static_stats, capped(&dynamic_stats))
+    }
+
+    /// Secondary stats from the base primary stats with `passives` applied on both sides of the conversion.
+    fn secondary_with<'a>(&self, passives: impl Iterator<Item = &'a CustomPassive> + Clone) -> SecondaryStats {
+        let primary = apply_passives(&self.base_primary(), TargetType::Primary, passives.clone());
+        let secondary = self.class.class_model.secondary_stats_convert(&self.level, &primary);
+        apply_passives(&secondary, TargetType::Secondary, passives)
+    }
+}
+
+impl Build {
+    /// Loads one saved build, brings it up to the current format and recomputes its stats.
+    pub fn from_json(json: &str) -> Result<Build, BackendError> {
+        let mut build: Build = serde_json::from_str(json).map_err(|e| BackendError::InvalidBuild(format!("Error: {e} ")))?;
+        build.migrate();
+        build.refresh_stats();
+        Ok(build)
+    }
+
+    /// Rewrites an older save so its passives keep the effect they had when it was saved.
+    fn migrate(&mut self) {
+        if self.version < 1 {
+            // Version 0 subtracted flat Heal In on the player. The gear's own trait passives
+            // are left alone, they're rebuilt from the trait table and were meant as written.
+            let mut traits = self.equipment.trait_passives();
+            for passive in self.passives.iter_mut() {
+                match traits.iter().position(|t| t == passive) {
+                    Some(i) => { traits.remove(i); }
+                    None => flip_heal_in(passive),
+                }
+            }
+            let on_cast = self.skills.iter_mut().flat_map(|(_, passives, _)| passives)
+                .chain(self.enemy_skills.iter_mut().flat_map(|(_, passives, _)| passives));
+            on_cast.for_each(flip_heal_in);
+        }
+        self.version = Build::VERSION;
+    }
+
+    pub fn to_json(&self) -> Result<String, BackendError> {
+        serde_json::to_string(self).map_err(|e| BackendError::InvalidBuild(format!("Error: {e} ")))
+    }
+}
+
+/// Flips a flat Heal In passive on the player from the version 0 convention to the current one.
+fn flip_heal_in(passive: &mut CustomPassive) {
+    if passive.target_type == TargetType::Secondary && passive.stat_name == StatKey::HealIn && passive.operation_type == OperationType::Additive {
+        passive.value = -passive.value;
+    }
+}
+
+/// Loads the app's saved builds (a name to build map), migrates them and recomputes their stats.
+pub fn load_builds(json: &str) -> Result<HashMap<String, Build>, BackendError> {
+    let mut builds: HashMap<String, Build> = serde_json::from_str(json).map_err(|e| BackendError::InvalidBuild(format!("Error: {e} ")))?;
+    for build in builds.values_mut() {
+        build.migrate();
+        build.refresh_stats();
+    }
+    Ok(builds)
+}
+
+impl Loadout for Build {
+    fn stat_layers(&self, buffs: &[CustomPassive]) -> StatLayers { Build::stat_layers(self, buffs) }
+    fn weapon(&self) -> &Weapon { &self.weapon }
+    fn skills(&self) -> &[(Skill, Vec<CustomPassive>, bool)] { &self.skills }
+    fn enemy(&self) -> &EnemySecondaryStats { &self.enemy }
+    fn enemy_skills(&self) -> &[(EnemySkill, Vec<CustomPassive>, bool)] { &self.enemy_skills }
+    fn enemy_phases(&self) -> &[EnemyPhase] { &self.enemy_phases }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_stats_applies_permanent_passives_only() {
+        let mut build = Build::default();
+        let base = build.clone();
+        build.passives.push(CustomPassive { target_type: TargetType::Primary, stat_name: StatKey::Strength, value: 50.0, operation_type: OperationType::Additive, duration: None });
+        build.passives.push(CustomPassive { target_type: TargetType::Secondary, stat_name: StatKey::AllOut, value: 10.0, operation_type: OperationType::Additive, duration: Some(5000) });
+        build.refresh_stats();
+        assert_eq!(build.primary_stats.strength, base.primary_stats.strength + 50);
+        assert_eq!(build.secondary_stats.all_out, base.secondary_stats.all_out);
+        assert_eq!(build.stat_layers(&[]).dynamic_stats, build.secondary_stats);
+    }
+
+    #[test]
+    fn test_buffs_share_layers_with_permanent_passives() {
+        let mut build = Build::default();
+        build.passives.push(CustomPassive { target_type: TargetType::Primary, stat_name: StatKey::Strength, value: 1.5, operation_type: OperationType::Multiplicative, duration: None });
+        build.refresh_stats();
+        let buff = CustomPassive { target_type: TargetType::Primary, stat_name: StatKey::Strength, value: 100.0, operation_type: OperationType::Additive, duration: Some(5000) };
+
+        // (base + 100) × 1.5, not base × 1.5 + 100
+        let primary = apply_passives(&build.base_primary(), TargetType::Primary, [&build.passives[0], &buff]);
+        assert_eq!(primary.strength, ((build.base_primary().strength + 100) as f32 * 1.5).round() as i32);
+        let expected = build.class.class_model.secondary_stats_convert(&build.level, &primary);
+        assert_eq!(build.stat_layers(&[buff]).dynamic_stats, expected);
+
+        let (primary, _) = build.derivations();
+        assert!(primary.iter().any(|d| d.key == StatKey::Strength && d.is_modified()));
+    }
+
+    #[test]
+    fn test_layers_ignore_stale_primary_stats() {
+        let mut build = Build::default();
+        let fresh = build.stat_layers(&[]);
+        // Edited without refresh_stats
+        build.primary_stats.strength += 100;
+        let noop = CustomPassive { target_type: TargetType::Secondary, stat_name: StatKey::AllOut, value: 0.0, ..Default::default() };
+        let layers = build.stat_layers(&[noop]);
+        assert_eq!(layers.static_stats, fresh.static_stats);
+        assert_eq!(layers.static_stats, layers.dynamic_stats);
+    }
+
+    #[test]
+    fn test_load_saved_builds() {
+        let mut build = Build::default();
+        build.equipment.helm.level = 50;
+        let json = format!("{{\"main\": {}}}", build.to_json().unwrap());
+        let builds = load_builds(&json).unwrap();
+        // Stats are recomputed from the gear, not trusted from the save
+        let loaded = &builds["main"];
+        build.refresh_stats();
+        assert_eq!(loaded, &build);
+        assert!(Build::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_baseline_save_keeps_its_heal_in() {
+        let builds = load_builds(include_str!("../data/baseline_builds.json")).unwrap();
+        let build = &builds["Old Save"];
+        assert_eq!(build.version, Build::VERSION);
+        // Saved as -50 Heal In, which the old app subtracted
+        let heal_in = build.passives.iter().find(|p| p.stat_name == StatKey::HealIn).unwrap();
+        assert_eq!(heal_in.value, 50.0);
+        assert_eq!(build.secondary_stats.heal_in, Build::default().secondary_stats.heal_in + 50.0);
+        assert_eq!(build.secondary_stats.phy_in, Build::default().secondary_stats.phy_in - 10.0);
+
+        // Migrated once, saving and loading again changes nothing
+        assert_eq!(&Build::from_json(&build.to_json().unwrap()).unwrap(), build);
+    }
+
+    #[test]
+    fn test_migration_leaves_trait_passives_alone() {
+        let mut build = Build::default();
+        let mut equipment = build.equipment.clone();
+        equipment.cape.r#trait = Trait::Vainglory;
+        build.set_equipment(equipment);
+        build.skills[1].1.push(CustomPassive { target_type: TargetType::Secondary, stat_name: StatKey::HealIn, value: 20.0, ..Default::default() });
+        let mut json: serde_json::Value = serde_json::from_str(&build.to_json().unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("version");
+
+        let loaded = Build::from_json(&json.to_string()).unwrap();
+        assert_eq!(loaded.passives, build.passives);
+        assert_eq!(loaded.skills[1].1[0].value, -20.0);
+    }
+
+    #[test]
+    fn test_set_equipment_swaps_trait_passives() {
+        let mut build = Build::default();
+        let own = CustomPassive { target_type: TargetType::Secondary, stat_name: StatKey::Haste, value: 1.0, ..Default::default() };
+        build.passives.push(own.clone());
+        let mut equipment = build.equipment.clone();
+        equipment.helm.r#trait = Trait::Vainglory;
+        build.set_equipment(equipment.clone());
+        assert_eq!(build.passives.len(), 3);
+
+        equipment.helm.r#trait = Trait::Ether;
+        equipment.helm.pattern = EnhancementPattern::Hearty;
+        build.set_equipment(equipment);
+        assert_eq!(build.passives, [vec![own], Trait::Ether.passives()].concat());
+        assert_eq!(build.primary_stats.endurance, build.base_primary().endurance);
+    }
+
+    #[test]
+    fn test_vainglory_cuts_healing_received() {
+        let mut build = Build::default();
+        let before = build.secondary_stats.heal_in;
+        let mut equipment = build.equipment.clone();
+        equipment.cape.r#trait = Trait::Vainglory;
+        build.set_equipment(equipment);
+        assert_eq!(build.secondary_stats.heal_in, before - 50.0);
+    }
+}
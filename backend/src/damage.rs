@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use crate::{error::BackendError, player::SecondaryStats};
+use crate::{error::BackendError, monte_carlo::Rng, player::{SecondaryStats, StatLayers}};
 use serde::{Serialize, Deserialize};
 
 
@@ -133,6 +133,8 @@ pub enum Type {
     Magical,
     TrueDamage,
     DamageOverTime,
+    /// Restores HP instead of dealing damage, scaled by the caster's Heal Out and the target's Heal In.
+    Heal,
 }
 
 impl FromStr for Type {
@@ -144,6 +146,7 @@ impl FromStr for Type {
             "Magical" => Ok(Type::Magical),
             "TrueDamage" => Ok(Type::TrueDamage),
             "DamageOverTime" => Ok(Type::DamageOverTime),
+            "Heal" => Ok(Type::Heal),
             e => Err(BackendError::InvalidDamageType(format!("Error: {e} ")))
         }
     }
@@ -151,22 +154,114 @@ impl FromStr for Type {
 
 
 impl Type {
-    pub fn self_modifiers(&self, stat: &SecondaryStats, crit: bool) -> f32 {
-        let crit_mod = if crit { stat.crit_mod } else { 100.0 };
-
+    /// The stat layer this damage type reads from.
+    /// DoTs are snapshotted so they only see static stats, everything else hits with live stats.
+    pub fn layer<'a>(&self, stats: &'a StatLayers) -> &'a SecondaryStats {
         match self {
-            Type::Physical => { println!("{} --- {} --- {}", (stat.all_out / 100.0), (stat.phy_out / 100.0), (crit_mod / 100.0)); (stat.all_out / 100.0) * (stat.phy_out / 100.0) * (crit_mod / 100.0) },
-            Type::Magical => (stat.all_out / 100.0) * (stat.mag_out / 100.0) * (crit_mod / 100.0),
-            Type::TrueDamage => 1.0 * (crit_mod / 100.0),
-            // This isn't proper I'm just lazy
-            // To do this properly will need to determine certain secondary stats as Static and Dynamic
-            // All Out being a weird exception where it is both Static and Dynamic in the case of DoTs
-            // Generally only place where there are Static/Dynamic differences are DoTs
-            // For chrono2 function, DoTs only take in Static stats and not any Dynamic stats in calculation
-            Type::DamageOverTime => (stat.all_out / 100.0).powi(2) * (stat.mag_out / 100.0) * (stat.dot_out / 100.0)
+            Type::DamageOverTime => &stats.static_stats,
+            _ => &stats.dynamic_stats,
+        }
+    }
+
+    pub fn self_modifiers(&self, stats: &StatLayers, crit: bool) -> f32 {
+        product(&self.self_multipliers(stats, crit))
+    }
+
+    /// The caster side modifiers of this type one by one. Crit only shows up when the hit crits.
+    pub fn self_multipliers(&self, stats: &StatLayers, crit: bool) -> Vec<Multiplier> {
+        let stat = self.layer(stats);
+        let mut multipliers = match self {
+            Type::Physical => vec![
+                Multiplier::new("All Out", stat.all_out / 100.0),
+                Multiplier::new("Phy Out", stat.phy_out / 100.0),
+            ],
+            Type::Magical => vec![
+                Multiplier::new("All Out", stat.all_out / 100.0),
+                Multiplier::new("Mag Out", stat.mag_out / 100.0),
+            ],
+            Type::TrueDamage => vec![],
+            // DoTs only read the static layer, so timed buffs cast after the DoT don't change it.
+            // All Out is the odd one out: Chrono2 treats it as both static and dynamic for DoTs,
+            // that isn't modelled here. DoTs don't crit.
+            Type::DamageOverTime => return vec![
+                Multiplier::new("All Out", stat.all_out / 100.0),
+                Multiplier::new("Mag Out", stat.mag_out / 100.0),
+                Multiplier::new("DoT Out", stat.dot_out / 100.0),
+            ],
+            Type::Heal => vec![Multiplier::new("Heal Out", stat.heal_out / 100.0)],
+        };
+        if crit {
+            multipliers.push(Multiplier::new("Crit", stat.crit_mod / 100.0));
+        }
+        multipliers
+    }
+
+}
+
+/// One named factor of a damage or heal calculation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Multiplier {
+    pub name: String,
+    pub value: f32,
+}
+
+impl Multiplier {
+    pub fn new(name: &str, value: f32) -> Self {
+        Multiplier { name: name.to_string(), value }
+    }
+}
+
+/// All multipliers applied one after the other.
+pub fn product(multipliers: &[Multiplier]) -> f32 {
+    multipliers.iter().fold(1.0, |acc, m| acc * m.value)
+}
+
+/// Which damage function a skill goes through.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum DamageFormula {
+    #[default] Standard,
+    Chrono2,
+}
+
+impl FromStr for DamageFormula {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Standard" => Ok(DamageFormula::Standard),
+            "Chrono2" => Ok(DamageFormula::Chrono2),
+            e => Err(BackendError::InvalidDamageFormula(format!("Error: {e} ")))
         }
     }
+}
+
+/// Chrono2 damage function.
+///
+/// Direct hits read live (dynamic) stats exactly like the standard path.
+/// DoTs only take static stats, with All Out as the exception: it is applied once from the
+/// static layer when the DoT is snapshotted and once more from the dynamic layer.
+/// In-game damage is always a whole number so the result is rounded.
+pub fn chrono2(skill: &Skill, weapon: &Weapon, stats: &StatLayers, crit: bool) -> f32 {
+    let type_final_modifier = product(&chrono2_multipliers(skill, stats, crit));
+    let dsrc_value = skill.dsrc.compute(weapon, skill.damage_type.layer(stats));
+    (dsrc_value * type_final_modifier * weapon.boost.multiplier() * skill.damage * skill.stacking_modifier()).round()
+}
 
+/// The type modifiers Chrono2 applies, which only differ from `Type::self_multipliers` for DoTs.
+pub fn chrono2_multipliers(skill: &Skill, stats: &StatLayers, crit: bool) -> Vec<Multiplier> {
+    let crit = skill.is_crit(crit);
+    match skill.damage_type {
+        Type::DamageOverTime => {
+            let snapshot = &stats.static_stats;
+            vec![
+                Multiplier::new("All Out (static)", snapshot.all_out / 100.0),
+                Multiplier::new("All Out (dynamic)", stats.dynamic_stats.all_out / 100.0),
+                Multiplier::new("Mag Out", snapshot.mag_out / 100.0),
+                Multiplier::new("DoT Out", snapshot.dot_out / 100.0),
+            ]
+        }
+        _ => skill.damage_type.self_multipliers(stats, crit),
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
@@ -189,6 +284,33 @@ impl FromStr for Target {
     }
 }
 
+/// Chance (0.0 to 1.0) that an attack lands, from the attacker's hit chance against the defender's dodge.
+/// Works the same for the player attacking the enemy and the enemy attacking the player.
+pub fn land_chance(hit_chance: f32, dodge: f32) -> f32 {
+    ((hit_chance - dodge) / 100.0).clamp(0.0, 1.0)
+}
+
+/// A heal split into the HP it actually restored and the part lost to the max HP cap.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct HealResult {
+    pub effective: f32,
+    pub overheal: f32,
+}
+
+impl HealResult {
+    /// Heals `amount` (already scaled by Heal In) onto a target at `current_hp` out of `max_hp`.
+    pub fn apply(amount: f32, current_hp: f32, max_hp: f32) -> Self {
+        let missing = (max_hp - current_hp).max(0.0);
+        let effective = amount.max(0.0).min(missing);
+        HealResult { effective, overheal: amount.max(0.0) - effective }
+    }
+
+    pub fn add(&mut self, other: &HealResult) {
+        self.effective += other.effective;
+        self.overheal += other.overheal;
+    }
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ForceResult {
     Hit,
@@ -200,20 +322,316 @@ pub struct Properties {
     pub force_result: Option<ForceResult>,
     pub add_crit: Option<f32>,
     pub mana_back: Option<u32>,
-    pub hp_back: Option<DamageSource>,
-    pub stacking: Option<StackingFunction>
+    pub hp_back: Option<HpBack>,
+    pub stacking: Option<StackingFunction>,
+    #[serde(default)]
+    pub dot: Option<DotEffect>,
+    /// Heal over time on the caster, ticks the same way a DoT does.
+    #[serde(default)]
+    pub hot: Option<DotEffect>,
 }
 
+/// Lifesteal: HP a skill gives back to the caster when it lands.
+/// Untagged so saves from when `hp_back` was a bare `DamageSource` still load as `Source`.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum HpBack {
+    /// Percent of the damage the hit dealt.
+    Percent(f32),
+    /// A flat amount worked out from a damage source, like `DamageSource::cHPm` for a max HP heal.
+    Source(DamageSource),
+}
+
+impl HpBack {
+    /// HP given back before the caster's Heal In. `dealt` is what the hit did to the enemy.
+    pub fn amount(&self, dealt: f32, weapon: &Weapon, stats: &SecondaryStats) -> f32 {
+        match self {
+            HpBack::Percent(p) => dealt.max(0.0) * p / 100.0,
+            HpBack::Source(source) => source.compute(weapon, stats),
+        }
+    }
+}
+
+/// What happens when a DoT is applied to a target that already has it.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum DotRefresh {
+    /// Adds a stack, resets the duration and takes a fresh snapshot.
+    #[default] Refresh,
+    /// Adds a stack and the full duration on top of what's left, keeping the old snapshot.
+    Extend,
+    /// Nothing happens until the running DoT expires.
+    Keep,
+}
+
+impl FromStr for DotRefresh {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Refresh" => Ok(DotRefresh::Refresh),
+            "Extend" => Ok(DotRefresh::Extend),
+            "Keep" => Ok(DotRefresh::Keep),
+            e => Err(BackendError::InvalidDotRefresh(format!("Error: {e} ")))
+        }
+    }
+}
+
+/// Damage over time a skill leaves on its target when it lands.
+/// Times are in ms like `Skill::cd`, `damage` is per tick and per stack.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DotEffect {
+    pub damage: f32,
+    pub dsrc: DamageSource,
+    pub tick_interval: u32,
+    pub duration: u32,
+    pub max_stacks: u32,
+    #[serde(default)]
+    pub refresh: DotRefresh,
+}
+
+impl Default for DotEffect {
+    fn default() -> Self {
+        DotEffect {
+            damage: 0.1,
+            dsrc: DamageSource::SP1,
+            tick_interval: 2000,
+            duration: 10000,
+            max_stacks: 1,
+            refresh: DotRefresh::Refresh,
+        }
+    }
+}
+
+impl DotEffect {
+    /// Number of ticks a single application deals.
+    pub fn ticks(&self) -> u32 {
+        self.duration.checked_div(self.tick_interval).unwrap_or(0)
+    }
+
+    /// Damage of one tick for one stack, frozen with the caster's stats at application.
+    /// Goes through the source skill's damage function as `Type::DamageOverTime`, so it only reads
+    /// the static layer (plus dynamic All Out under Chrono2). Ticks don't crit or miss.
+    pub fn snapshot(&self, source: &Skill, weapon: &Weapon, stats: &StatLayers) -> f32 {
+        let tick = Skill {
+            damage: self.damage,
+            dsrc: self.dsrc,
+            damage_type: Type::DamageOverTime,
+            properties: Properties { stacking: None, dot: None, hot: None, ..source.properties },
+            ..*source
+        };
+        tick.compute(weapon, stats, false)
+    }
+
+    /// HoT version of `snapshot`: one tick of healing for one stack, before the target's Heal In.
+    /// Heals have no static/dynamic split, so this freezes the caster's live Heal Out at application.
+    pub fn heal_snapshot(&self, source: &Skill, weapon: &Weapon, stats: &StatLayers) -> f32 {
+        let tick = Skill {
+            damage: self.damage,
+            dsrc: self.dsrc,
+            damage_type: Type::Heal,
+            properties: Properties { stacking: None, dot: None, hot: None, ..source.properties },
+            ..*source
+        };
+        tick.compute(weapon, stats, false)
+    }
+}
+
+/// A DoT ticking on a target. Times are in seconds of fight time.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ActiveDot {
+    /// Index of the skill that applied it, ticks are credited to that skill.
+    pub source: usize,
+    pub stacks: u32,
+    pub tick_damage: f32,
+    pub interval: f32,
+    pub next_tick: f32,
+    pub expires_at: f32,
+}
+
+impl ActiveDot {
+    /// Damage the next tick deals with all stacks, before the target's modifiers.
+    pub fn damage(&self) -> f32 {
+        self.tick_damage * self.stacks as f32
+    }
+
+    /// Moves on to the next tick. Returns `false` once the DoT has run out.
+    pub fn advance(&mut self) -> bool {
+        self.next_tick += self.interval;
+        self.is_running()
+    }
+
+    // Small slack so a tick landing exactly on expiry isn't lost to float error
+    pub fn is_running(&self) -> bool {
+        self.next_tick <= self.expires_at + 1e-4
+    }
+
+    /// Applies `effect` from skill `source` at time `now` following its refresh rule.
+    pub fn apply(dots: &mut Vec<ActiveDot>, source: usize, effect: &DotEffect, tick_damage: f32, now: f32) {
+        let duration = effect.duration as f32 / 1000.0;
+        let interval = (effect.tick_interval as f32 / 1000.0).max(0.001);
+        let max_stacks = effect.max_stacks.max(1);
+
+        if let Some(dot) = dots.iter_mut().find(|d| d.source == source) {
+            match effect.refresh {
+                DotRefresh::Refresh => {
+                    dot.stacks = (dot.stacks + 1).min(max_stacks);
+                    dot.tick_damage = tick_damage;
+                    dot.expires_at = now + duration;
+                }
+                DotRefresh::Extend => {
+                    dot.stacks = (dot.stacks + 1).min(max_stacks);
+                    dot.expires_at += duration;
+                }
+                DotRefresh::Keep => {}
+            }
+            return;
+        }
+
+        if effect.ticks() == 0 {
+            return;
+        }
+        dots.push(ActiveDot {
+            source,
+            stacks: 1,
+            tick_damage,
+            interval,
+            next_tick: now + interval,
+            expires_at: now + duration,
+        });
+    }
+}
+
+/// How stacks turn into a damage multiplier.
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
 pub enum StackingFormula {
+    /// `stacks / 2`: the skill does nothing without stacks and needs 2 to hit for 100%.
     Weird,
+    /// `1 + stacks / 2`: full damage at 0 stacks, each stack adds another 50%.
     Normal
 }
+
+impl StackingFormula {
+    pub fn modifier(&self, stacks: u32) -> f32 {
+        match self {
+            StackingFormula::Weird => stacks as f32 / 2.0,
+            StackingFormula::Normal => (stacks as f32 / 2.0) + 1.0,
+        }
+    }
+}
+
+impl FromStr for StackingFormula {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Weird" => Ok(StackingFormula::Weird),
+            "Normal" => Ok(StackingFormula::Normal),
+            e => Err(BackendError::InvalidStackingFormula(format!("Error: {e} ")))
+        }
+    }
+}
+
+/// What gives a stacking skill its stacks.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum StackGain {
+    /// One stack every time any skill is cast, auto attacks included.
+    #[default] PerCast,
+    /// One stack every time a direct hit lands on the enemy. Misses and DoT ticks don't count.
+    PerHit,
+}
+
+impl FromStr for StackGain {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "PerCast" => Ok(StackGain::PerCast),
+            "PerHit" => Ok(StackGain::PerHit),
+            e => Err(BackendError::InvalidStackGain(format!("Error: {e} ")))
+        }
+    }
+}
+
+/// Damage that scales with stacks built up by other casts or hits.
+/// `stacks` is the count the skill is computed with outside a fight, and what a fight starts with.
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
 pub struct StackingFunction {
     stack_limit: u32,
     stacks: u32,
-    formula: StackingFormula
+    formula: StackingFormula,
+    #[serde(default)]
+    gain: StackGain,
+    /// ms after the last gained stack until all stacks drop, `None` never decays
+    #[serde(default)]
+    decay: Option<u32>,
+    /// Whether casting the skill spends its stacks
+    #[serde(default)]
+    consume: bool,
+}
+
+impl StackingFunction {
+    pub fn new(stack_limit: u32, formula: StackingFormula, gain: StackGain, decay: Option<u32>, consume: bool) -> Self {
+        StackingFunction { stack_limit, stacks: 0, formula, gain, decay, consume }
+    }
+
+    /// Same function holding `stacks`, clamped to the limit.
+    pub fn with_stacks(self, stacks: u32) -> Self {
+        StackingFunction { stacks: stacks.min(self.stack_limit), ..self }
+    }
+
+    pub fn stack_limit(&self) -> u32 { self.stack_limit }
+    pub fn stacks(&self) -> u32 { self.stacks }
+    pub fn formula(&self) -> StackingFormula { self.formula }
+    pub fn gain(&self) -> StackGain { self.gain }
+    pub fn decay(&self) -> Option<u32> { self.decay }
+    pub fn consume(&self) -> bool { self.consume }
+
+    /// Damage multiplier at the current stack count.
+    pub fn modifier(&self) -> f32 {
+        self.formula.modifier(self.stacks.min(self.stack_limit))
+    }
+}
+
+/// Stack count of a stacking skill as it changes through a fight. Times are in seconds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StackTracker {
+    pub function: StackingFunction,
+    /// When the current stacks decay, `None` if they don't
+    pub expires_at: Option<f32>,
+}
+
+impl StackTracker {
+    pub fn new(function: StackingFunction) -> Self {
+        StackTracker { function, expires_at: None }
+    }
+
+    pub fn stacks(&self) -> u32 {
+        self.function.stacks
+    }
+
+    /// Adds a stack at `now` and restarts the decay timer.
+    pub fn gain(&mut self, now: f32) {
+        self.function.stacks = (self.function.stacks + 1).min(self.function.stack_limit);
+        self.expires_at = self.function.decay.map(|ms| now + ms as f32 / 1000.0);
+    }
+
+    /// Drops every stack if the decay timer ran out by `now`.
+    pub fn update(&mut self, now: f32) {
+        if let Some(t) = self.expires_at
+            && now >= t
+        {
+            self.function.stacks = 0;
+            self.expires_at = None;
+        }
+    }
+
+    /// Called after the skill is cast, spends the stacks if the function consumes them.
+    pub fn on_use(&mut self) {
+        if self.function.consume {
+            self.function.stacks = 0;
+            self.expires_at = None;
+        }
+    }
 }
 
 
@@ -226,33 +644,109 @@ pub struct Skill {
     pub cd: u32,
     pub mp: u32,
     pub target: Target,
-    pub properties: Properties
+    pub properties: Properties,
+    #[serde(default)]
+    pub formula: DamageFormula,
 }
 
 impl Skill {
-    pub fn compute(&self, weapon: &Weapon, secondary: &SecondaryStats, crit: bool) -> f32 {
-        let mut crit = crit;
-        if let Some(val) = &self.properties.force_result && val == &ForceResult::Crit {
-            crit = true;
-        }
-    
-        let mut stacking_mod = 0.0;
-        if let Some(stacking) = &self.properties.stacking {
-            match stacking.formula {
-                StackingFormula::Weird => {
-                    stacking_mod = (stacking.stacks as f32).clamp(0.0, stacking.stack_limit as f32) / 2.0;
-                }
-                StackingFormula::Normal => {
-                    stacking_mod = ((stacking.stacks as f32).clamp(0.0, stacking.stack_limit as f32) / 2.0) + 1.0;
-                }
-            }
+    pub fn compute(&self, weapon: &Weapon, stats: &StatLayers, crit: bool) -> f32 {
+        if self.formula == DamageFormula::Chrono2 {
+            return chrono2(self, weapon, stats, crit);
         }
-        
-        let type_final_modifier = &self.damage_type.self_modifiers(secondary, crit);
-        let dsrc_value = &self.dsrc.compute(weapon, secondary);
-        println!("{} --- {} --- {}", dsrc_value, type_final_modifier, weapon.boost.multiplier());
+
+        let crit = self.is_crit(crit);
+        let stacking_mod = self.stacking_modifier();
+
+        let type_final_modifier = &self.damage_type.self_modifiers(stats, crit);
+        let dsrc_value = &self.dsrc.compute(weapon, self.damage_type.layer(stats));
         dsrc_value * type_final_modifier * weapon.boost.multiplier() * self.damage * stacking_mod
     }
+
+    /// Expected damage of one cast: crit weighted and scaled by the chance to land against `dodge`.
+    pub fn expected(&self, weapon: &Weapon, stats: &StatLayers, dodge: f32) -> f32 {
+        let secondary = self.damage_type.layer(stats);
+        self.crit_weighted(weapon, stats) * self.land_chance(secondary.hit_chance, dodge)
+    }
+
+    /// One landed cast with crits weighted by their chance.
+    fn crit_weighted(&self, weapon: &Weapon, stats: &StatLayers) -> f32 {
+        let crit = self.crit_chance(self.damage_type.layer(stats));
+        let non_crit = self.compute(weapon, stats, false);
+        let crit_dmg = self.compute(weapon, stats, true);
+        non_crit * (1.0 - crit) + crit_dmg * crit
+    }
+
+    /// Rolls a single cast: a miss deals nothing, otherwise crit is rolled on its own.
+    pub fn roll(&self, weapon: &Weapon, stats: &StatLayers, dodge: f32, rng: &mut Rng) -> f32 {
+        self.roll_hit(weapon, stats, dodge, rng).unwrap_or(0.0)
+    }
+
+    /// Same as `roll` but tells a miss (`None`) apart from a hit that deals nothing,
+    /// which matters for skills whose only effect is a DoT.
+    pub fn roll_hit(&self, weapon: &Weapon, stats: &StatLayers, dodge: f32, rng: &mut Rng) -> Option<f32> {
+        let secondary = self.damage_type.layer(stats);
+        if !rng.chance(self.land_chance(secondary.hit_chance, dodge)) {
+            return None;
+        }
+        let crit = rng.chance(self.crit_chance(secondary));
+        Some(self.compute(weapon, stats, crit))
+    }
+
+    /// Expected healing of one cast of a `Type::Heal` skill onto a target with `heal_in`.
+    /// Heals can't miss whatever their target, crits are weighted like damage.
+    pub fn expected_heal(&self, weapon: &Weapon, stats: &StatLayers, heal_in: f32) -> f32 {
+        self.crit_weighted(weapon, stats) * (heal_in / 100.0)
+    }
+
+    /// Rolls the crit on one cast of a `Type::Heal` skill onto a target with `heal_in`.
+    pub fn roll_heal(&self, weapon: &Weapon, stats: &StatLayers, heal_in: f32, rng: &mut Rng) -> f32 {
+        let crit = rng.chance(self.crit_chance(self.damage_type.layer(stats)));
+        self.compute(weapon, stats, crit) * (heal_in / 100.0)
+    }
+
+    /// Chance this skill lands. `ForceResult::Hit` bypasses the roll,
+    /// and skills that don't target the enemy can't be dodged.
+    pub fn land_chance(&self, hit_chance: f32, dodge: f32) -> f32 {
+        if self.properties.force_result == Some(ForceResult::Hit) || self.target != Target::Enemy {
+            return 1.0;
+        }
+        land_chance(hit_chance, dodge)
+    }
+
+    pub fn crit_chance(&self, stats: &SecondaryStats) -> f32 {
+        if self.properties.force_result == Some(ForceResult::Crit) {
+            return 1.0;
+        }
+        ((stats.crit_chance / 100.0) + self.properties.add_crit.unwrap_or(0.0)).clamp(0.0, 1.0)
+    }
+
+    /// Whether this hit crits, with `ForceResult::Crit` overriding the roll.
+    pub fn is_crit(&self, crit: bool) -> bool {
+        crit || self.properties.force_result == Some(ForceResult::Crit)
+    }
+
+    /// MP this skill takes to cast with the caster's Mana Consumption.
+    pub fn mana_cost(&self, stats: &SecondaryStats) -> i32 {
+        stats.mana_cost(self.mp)
+    }
+
+    /// HP this skill steals back when it lands, scaled by the caster's Heal In but not capped.
+    /// `dealt` is the damage the hit did, `landed` how much it landed (0 to 1).
+    pub fn lifesteal(&self, dealt: f32, landed: f32, weapon: &Weapon, stats: &SecondaryStats) -> f32 {
+        let Some(hp_back) = self.properties.hp_back else { return 0.0 };
+        let amount = match hp_back {
+            // Already 0 on a miss and land weighted in expected damage
+            HpBack::Percent(_) => hp_back.amount(dealt, weapon, stats),
+            HpBack::Source(_) => hp_back.amount(dealt, weapon, stats) * landed,
+        };
+        amount * (stats.heal_in / 100.0)
+    }
+
+    /// Damage multiplier from the skill's stacks, 1.0 when it doesn't stack.
+    pub fn stacking_modifier(&self) -> f32 {
+        self.properties.stacking.map_or(1.0, |stacking| stacking.modifier())
+    }
 }
 
 impl Default for Skill {
@@ -270,53 +764,370 @@ impl Default for Skill {
                 add_crit: None,
                 mana_back: None,
                 hp_back: None,
+                dot: None,
+                hot: None,
             },
+            formula: DamageFormula::Standard,
         }
     }
 }
 
 
 
-#[test]
-fn test_skill_compute() {
-    let skill = Skill {
-        damage: 1.1,
-        dsrc: DamageSource::AP2,
-        damage_type: Type::Physical,
-        cd: 1000,
-        mp: 0,
-        target: Target::Enemy,
-        properties: Properties { force_result: None, add_crit: None, mana_back: None, hp_back: None, stacking: None }
-        
-    };
-    let weapon = Weapon::default();
-    let secondary = SecondaryStats {
-        phy_out: 100.0,
-        phy_in: 100.0,
-        mag_out: 138.35,
-        mag_in: 100.0 - 38.35,
-        all_in: 100.0,
-        all_out: 120.0,
-        heal_in: 100.0,
-        heal_out: 100.0,
-        dot_in: 100.0,
-        dot_out: 100.0,
-        crit_chance: 56.44,
-        attack_power: 1156.0,
-        spell_power: 798.0,
-        mana_consumption: 100.0,
-        current_hp: 2310,
-        hp: 2310,
-        current_mp: 100,
-        dodge: 23.87,
-        haste: 32.72,
-        mp: 100,
-        hit_chance: 118.63,
-        crit_mod: 323.97,
-    };
-    let crit = false;
-    // 1202 (3894 crit)
-    let result = skill.compute(&weapon, &secondary, crit);
-    println!("RESULT OF DMG: {}", result );
-    assert!(result > 0.0);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stat sheet the in-game reference hits were taken with
+    fn reference_stats() -> SecondaryStats {
+        SecondaryStats {
+            phy_out: 100.0,
+            phy_in: 100.0,
+            mag_out: 138.35,
+            mag_in: 100.0 - 38.35,
+            all_in: 100.0,
+            all_out: 120.0,
+            heal_in: 100.0,
+            heal_out: 100.0,
+            dot_in: 100.0,
+            dot_out: 100.0,
+            crit_chance: 56.44,
+            attack_power: 1156.0,
+            spell_power: 798.0,
+            mana_consumption: 100.0,
+            current_hp: 2310,
+            hp: 2310,
+            current_mp: 100,
+            dodge: 23.87,
+            haste: 32.72,
+            mp: 100,
+            hit_chance: 118.63,
+            crit_mod: 323.97,
+        }
+    }
+
+    fn reference_skill() -> Skill {
+        Skill {
+            damage: 1.1,
+            dsrc: DamageSource::AP2,
+            damage_type: Type::Physical,
+            cd: 1000,
+            mp: 0,
+            target: Target::Enemy,
+            properties: Properties { force_result: None, add_crit: None, mana_back: None, hp_back: None, stacking: None, dot: None, hot: None },
+            formula: DamageFormula::Standard,
+        }
+    }
+
+    #[test]
+    fn test_skill_compute() {
+        let skill = reference_skill();
+        let weapon = Weapon::default();
+        let secondary = reference_stats();
+        let crit = false;
+        // 1202 (3894 crit)
+        let stats = StatLayers::from(secondary);
+        let result = skill.compute(&weapon, &stats, crit);
+        assert!(result > 0.0);
+        let breakdown = skill.breakdown(&weapon, &stats, crit, None);
+        assert_eq!(breakdown.value, result);
+        assert_eq!((breakdown.source, breakdown.damage_type, breakdown.crit), (DamageSource::AP2, Type::Physical, false));
+        assert!(breakdown.multipliers.iter().any(|m| m.name == "Skill Damage" && m.value == 1.1));
+        assert!((breakdown.source_value * product(&breakdown.multipliers) - result).abs() < 1e-2);
+        assert_eq!(breakdown.final_value, result);
+    }
+
+    #[test]
+    fn test_chrono2_matches_in_game_hits() {
+        let skill = Skill { formula: DamageFormula::Chrono2, ..reference_skill() };
+        let weapon = Weapon::default();
+        let stats = StatLayers::from(reference_stats());
+
+        // Weapon damage has a small random spread in-game, so pin within 0.5%
+        let hit = skill.compute(&weapon, &stats, false);
+        let crit = skill.compute(&weapon, &stats, true);
+        assert!((hit - 1202.0).abs() / 1202.0 < 0.005, "hit was {hit}");
+        assert!((crit - 3894.0).abs() / 3894.0 < 0.005, "crit was {crit}");
+        assert_eq!(hit, hit.round());
+    }
+
+    #[test]
+    fn test_chrono2_dot_all_out_static_and_dynamic() {
+        let weapon = Weapon::default();
+        let dot = Skill { damage: 1.0, dsrc: DamageSource::SP1, damage_type: Type::DamageOverTime, formula: DamageFormula::Chrono2, ..Default::default() };
+        let standard_dot = Skill { formula: DamageFormula::Standard, ..dot };
+
+        let base = SecondaryStats { spell_power: 500.0, all_out: 120.0, ..Default::default() };
+        let standard = standard_dot.compute(&weapon, &StatLayers::from(base.clone()), false);
+        let chrono = dot.compute(&weapon, &StatLayers::from(base.clone()), false);
+        assert_eq!(chrono, (standard * 1.2).round());
+
+        // Only the dynamic All Out is picked up from buffs, every other live stat is ignored
+        let mut buffed = base.clone();
+        buffed.modify_all_out(30.0, false);
+        buffed.modify_mag_out(50.0, false);
+        buffed.modify_dot_out(50.0, false);
+        buffed.spell_power += 300.0;
+        let chrono_buffed = dot.compute(&weapon, &StatLayers::new(base, buffed), false);
+        assert_eq!(chrono_buffed, (standard * 1.5).round());
+    }
+
+    #[test]
+    fn test_land_chance() {
+        assert_eq!(land_chance(90.0, 10.0), 0.8);
+        assert_eq!(land_chance(118.63, 10.0), 1.0);
+        assert_eq!(land_chance(20.0, 50.0), 0.0);
+    }
+
+    #[test]
+    fn test_skill_land_chance_force_hit() {
+        let skill = reference_skill();
+        assert_eq!(skill.land_chance(90.0, 40.0), 0.5);
+
+        let forced = Skill { properties: Properties { force_result: Some(ForceResult::Hit), ..skill.properties }, ..skill };
+        assert_eq!(forced.land_chance(90.0, 40.0), 1.0);
+
+        let on_self = Skill { target: Target::Yourself, ..skill };
+        assert_eq!(on_self.land_chance(90.0, 40.0), 1.0);
+    }
+
+    #[test]
+    fn test_expected_damage() {
+        let skill = reference_skill();
+        let weapon = Weapon::default();
+        let stats = StatLayers::from(SecondaryStats { attack_power: 1000.0, crit_chance: 25.0, crit_mod: 200.0, hit_chance: 90.0, ..Default::default() });
+
+        let hit = skill.compute(&weapon, &stats, false);
+        // 25% crits for double damage, 80% of casts land against 10 dodge
+        let expected = skill.expected(&weapon, &stats, 10.0);
+        assert!((expected - hit * 1.25 * 0.8).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_roll_averages_to_expected() {
+        let skill = reference_skill();
+        let weapon = Weapon::default();
+        let stats = StatLayers::from(SecondaryStats { attack_power: 1000.0, crit_chance: 30.0, crit_mod: 200.0, hit_chance: 90.0, ..Default::default() });
+        let mut rng = Rng::new(1);
+
+        let n = 20_000;
+        let avg = (0..n).map(|_| skill.roll(&weapon, &stats, 20.0, &mut rng)).sum::<f32>() / n as f32;
+        let expected = skill.expected(&weapon, &stats, 20.0);
+        assert!((avg - expected).abs() / expected < 0.02);
+    }
+
+    #[test]
+    fn test_damage_formula_from_str() {
+        assert_eq!(DamageFormula::from_str("Chrono2").unwrap(), DamageFormula::Chrono2);
+        assert_eq!(DamageFormula::from_str("Standard").unwrap(), DamageFormula::Standard);
+        assert!(DamageFormula::from_str("chrono3").is_err());
+    }
+
+    #[test]
+    fn test_dot_reads_static_layer() {
+        let skill = Skill { damage: 1.0, dsrc: DamageSource::SP1, damage_type: Type::DamageOverTime, ..Default::default() };
+        let weapon = Weapon::default();
+        let base = SecondaryStats { spell_power: 500.0, ..Default::default() };
+        let mut buffed = base.clone();
+        buffed.modify_all_out(30.0, false);
+        buffed.modify_mag_out(25.0, false);
+        buffed.spell_power += 200.0;
+
+        let unbuffed = skill.compute(&weapon, &StatLayers::from(base.clone()), false);
+        let layered = skill.compute(&weapon, &StatLayers::new(base, buffed), false);
+        assert_eq!(unbuffed, layered);
+    }
+
+    #[test]
+    fn test_direct_damage_reads_dynamic_layer() {
+        let skill = Skill { damage: 1.0, dsrc: DamageSource::AP1, damage_type: Type::Physical, ..Default::default() };
+        let weapon = Weapon::default();
+        let base = SecondaryStats { attack_power: 500.0, ..Default::default() };
+        let mut buffed = base.clone();
+        buffed.modify_all_out(30.0, false);
+
+        let unbuffed = skill.compute(&weapon, &StatLayers::from(base.clone()), false);
+        let layered = skill.compute(&weapon, &StatLayers::new(base, buffed), false);
+        assert!((layered / unbuffed - 1.3).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_dot_ticks_over_duration() {
+        let effect = DotEffect { damage: 1.0, tick_interval: 2000, duration: 10000, ..Default::default() };
+        let mut dots = Vec::new();
+        ActiveDot::apply(&mut dots, 2, &effect, 100.0, 1.0);
+
+        let mut ticks = 0;
+        let mut dealt = 0.0;
+        while let Some(dot) = dots.first_mut() {
+            assert!((dot.next_tick - (1.0 + 2.0 * (ticks + 1) as f32)).abs() < 1e-4);
+            dealt += dot.damage();
+            ticks += 1;
+            if !dot.advance() {
+                dots.clear();
+            }
+        }
+        assert_eq!(ticks, effect.ticks());
+        assert_eq!(dealt, 500.0);
+    }
+
+    #[test]
+    fn test_dot_refresh_rules() {
+        let refresh = DotEffect { duration: 6000, max_stacks: 2, refresh: DotRefresh::Refresh, ..Default::default() };
+        let mut dots = Vec::new();
+        for (now, dmg) in [(0.0, 10.0), (1.0, 20.0), (2.0, 30.0)] {
+            ActiveDot::apply(&mut dots, 1, &refresh, dmg, now);
+        }
+        assert_eq!(dots.len(), 1);
+        assert_eq!(dots[0].stacks, 2);
+        assert_eq!(dots[0].tick_damage, 30.0);
+        assert_eq!(dots[0].expires_at, 8.0);
+        assert_eq!(dots[0].next_tick, 2.0);
+
+        let extend = DotEffect { refresh: DotRefresh::Extend, ..refresh };
+        let mut dots = Vec::new();
+        ActiveDot::apply(&mut dots, 1, &extend, 10.0, 0.0);
+        ActiveDot::apply(&mut dots, 1, &extend, 20.0, 1.0);
+        assert_eq!(dots[0].tick_damage, 10.0);
+        assert_eq!(dots[0].expires_at, 12.0);
+
+        let keep = DotEffect { refresh: DotRefresh::Keep, ..refresh };
+        let mut dots = Vec::new();
+        ActiveDot::apply(&mut dots, 1, &keep, 10.0, 0.0);
+        ActiveDot::apply(&mut dots, 1, &keep, 20.0, 1.0);
+        assert_eq!(dots[0].stacks, 1);
+        assert_eq!(dots[0].tick_damage, 10.0);
+        assert_eq!(dots[0].expires_at, 6.0);
+
+        // Different sources tick on their own
+        ActiveDot::apply(&mut dots, 2, &keep, 5.0, 1.0);
+        assert_eq!(dots.len(), 2);
+    }
+
+    #[test]
+    fn test_dot_snapshot_uses_static_layer() {
+        let effect = DotEffect { damage: 0.5, dsrc: DamageSource::SP1, ..Default::default() };
+        let source = Skill { damage: 2.0, damage_type: Type::Magical, ..Default::default() };
+        let weapon = Weapon::default();
+        let base = SecondaryStats { spell_power: 500.0, ..Default::default() };
+        let mut buffed = base.clone();
+        buffed.modify_mag_out(25.0, false);
+
+        let unbuffed = effect.snapshot(&source, &weapon, &StatLayers::from(base.clone()));
+        let layered = effect.snapshot(&source, &weapon, &StatLayers::new(base.clone(), buffed));
+        assert_eq!(unbuffed, layered);
+
+        let expected = DamageSource::SP1.compute(&weapon, &base) * weapon.boost.multiplier() * 0.5;
+        assert!((unbuffed - expected).abs() < 1e-3);
+        assert_eq!(DotRefresh::from_str("Extend").unwrap(), DotRefresh::Extend);
+    }
+
+    #[test]
+    fn test_heal_scales_with_heal_out_and_heal_in() {
+        let skill = Skill { damage: 1.0, dsrc: DamageSource::SP1, damage_type: Type::Heal, target: Target::Yourself, ..Default::default() };
+        let weapon = Weapon::default();
+        let base = SecondaryStats { spell_power: 500.0, crit_chance: 0.0, ..Default::default() };
+        let mut boosted = base.clone();
+        boosted.modify_heal_out(20.0, false);
+
+        let plain = skill.expected_heal(&weapon, &StatLayers::from(base.clone()), 100.0);
+        let raw = DamageSource::SP1.compute(&weapon, &base) * weapon.boost.multiplier();
+        assert!((plain - raw).abs() < 1e-3);
+
+        let heal_out = skill.expected_heal(&weapon, &StatLayers::from(boosted), 100.0);
+        assert!((heal_out / plain - 1.2).abs() < 1e-4);
+
+        let heal_in = skill.expected_heal(&weapon, &StatLayers::from(base), 50.0);
+        assert!((heal_in / plain - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_heal_aimed_at_enemy_cant_miss() {
+        let skill = Skill { damage: 1.0, dsrc: DamageSource::SP1, damage_type: Type::Heal, target: Target::Enemy, ..Default::default() };
+        let weapon = Weapon::default();
+        let stats = StatLayers::from(SecondaryStats { spell_power: 500.0, crit_chance: 0.0, hit_chance: 50.0, ..Default::default() });
+        let raw = skill.compute(&weapon, &stats, false);
+        assert!((skill.expected_heal(&weapon, &stats, 100.0) - raw).abs() < 1e-3);
+        // Rolled heals agree with the expected one
+        assert_eq!(skill.roll_heal(&weapon, &stats, 100.0, &mut Rng::new(3)), raw);
+    }
+
+    #[test]
+    fn test_heal_result_tracks_overheal() {
+        let result = HealResult::apply(300.0, 900.0, 1000.0);
+        assert_eq!(result, HealResult { effective: 100.0, overheal: 200.0 });
+
+        let mut total = HealResult::apply(50.0, 500.0, 1000.0);
+        total.add(&result);
+        assert_eq!(total, HealResult { effective: 150.0, overheal: 200.0 });
+        assert_eq!(HealResult::apply(100.0, 1000.0, 1000.0).effective, 0.0);
+    }
+
+    #[test]
+    fn test_stacking_formulas() {
+        assert_eq!(StackingFormula::Normal.modifier(0), 1.0);
+        assert_eq!(StackingFormula::Normal.modifier(3), 2.5);
+        assert_eq!(StackingFormula::Weird.modifier(0), 0.0);
+        assert_eq!(StackingFormula::Weird.modifier(2), 1.0);
+
+        let normal = StackingFunction::new(4, StackingFormula::Normal, StackGain::PerCast, None, false);
+        assert_eq!(normal.with_stacks(10).stacks(), 4);
+        assert_eq!(normal.with_stacks(10).modifier(), 3.0);
+
+        let base = Skill::default();
+        let stacked = Skill { properties: Properties { stacking: Some(normal.with_stacks(2)), ..base.properties }, ..base };
+        let stats = StatLayers::from(reference_stats());
+        let weapon = Weapon::default();
+        let ratio = stacked.compute(&weapon, &stats, false) / base.compute(&weapon, &stats, false);
+        assert!((ratio - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_stack_tracker_decay_and_consume() {
+        let function = StackingFunction::new(3, StackingFormula::Weird, StackGain::PerHit, Some(5000), true);
+        let mut tracker = StackTracker::new(function);
+        for now in [0.0, 1.0, 2.0, 3.0] {
+            tracker.gain(now);
+        }
+        assert_eq!(tracker.stacks(), 3);
+        assert_eq!(tracker.expires_at, Some(8.0));
+
+        tracker.update(7.9);
+        assert_eq!(tracker.stacks(), 3);
+        tracker.update(8.0);
+        assert_eq!(tracker.stacks(), 0);
+
+        tracker.gain(10.0);
+        tracker.on_use();
+        assert_eq!(tracker.stacks(), 0);
+
+        let kept = StackingFunction::new(3, StackingFormula::Normal, StackGain::PerCast, None, false);
+        let mut tracker = StackTracker::new(kept.with_stacks(1));
+        tracker.gain(0.0);
+        tracker.on_use();
+        tracker.update(1_000.0);
+        assert_eq!(tracker.stacks(), 2);
+    }
+
+    #[test]
+    fn test_lifesteal() {
+        let weapon = Weapon::default();
+        let stats = SecondaryStats { heal_in: 150.0, hp: 2000, ..Default::default() };
+        let percent = Skill { properties: Properties { hp_back: Some(HpBack::Percent(10.0)), ..Skill::default().properties }, ..Default::default() };
+        assert!((percent.lifesteal(1000.0, 1.0, &weapon, &stats) - 150.0).abs() < 1e-3);
+        assert_eq!(percent.lifesteal(0.0, 0.0, &weapon, &stats), 0.0);
+
+        let source = Skill { properties: Properties { hp_back: Some(HpBack::Source(DamageSource::cHPm)), ..Skill::default().properties }, ..Default::default() };
+        assert!((source.lifesteal(1000.0, 0.5, &weapon, &stats) - 1500.0).abs() < 1e-3);
+        assert_eq!(Skill::default().lifesteal(1000.0, 1.0, &weapon, &stats), 0.0);
+    }
+
+    #[test]
+    fn test_hp_back_loads_old_saves() {
+        let old: Option<HpBack> = serde_json::from_str("\"AP1\"").unwrap();
+        assert_eq!(old, Some(HpBack::Source(DamageSource::AP1)));
+        let percent: HpBack = serde_json::from_str("12.5").unwrap();
+        assert_eq!(percent, HpBack::Percent(12.5));
+        assert_eq!(serde_json::to_string(&percent).unwrap(), "12.5");
+    }
 }
@@ -1,209 +1,304 @@
-use serde::{Serialize, Deserialize};
-
-
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
-pub struct EnemySecondaryStats {
-    pub phy_out: f32,
-    pub phy_in: f32,
-    pub mag_out: f32,
-    pub mag_in: f32,
-    pub dot_out: f32,
-    pub dot_in: f32,
-    pub heal_out: f32,
-    pub heal_in: f32,
-    pub all_in: f32,
-    pub all_out: f32,
-    pub crit_chance: f32,
-    pub crit_mod: f32,
-    pub mana_consumption: f32,
-    pub haste: f32,
-    pub dodge: f32,
-    pub hit_chance: f32,
-    pub attack_power: f32,
-    pub spell_power: f32,
-    pub hp: i32,
-    pub current_hp: i32,
-    pub mp: i32,
-    pub current_mp: i32,
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use crate::{
+    damage::{ForceResult, Multiplier, Target, Type, land_chance, product},
+    error::BackendError,
+    monte_carlo::Rng,
+    passive::CustomPassive,
+    player::{SecondaryStats, StatLayers},
+    stat::{StatBlock, StatKey},
+};
+
+/// Enemies share the player's stat block, so passives, caps and modifiers work the same on both sides.
+pub type EnemySecondaryStats = SecondaryStats;
+
+impl SecondaryStats {
+    /// What an enemy starts from: more crit, haste and dodge than a fresh player.
+    pub fn enemy_default() -> Self {
+        SecondaryStats { crit_chance: 15.0, haste: 37.5, dodge: 10.0, ..Default::default() }
+    }
 }
 
-impl Default for EnemySecondaryStats {
-    fn default() -> Self {
-        Self::new()
+/// Reads enemy stats, with every field left out taken from `enemy_default` rather than the player's defaults.
+pub fn deserialize_enemy_stats<'de, D: Deserializer<'de>>(deserializer: D) -> Result<EnemySecondaryStats, D::Error> {
+    let listed = serde_json::Map::deserialize(deserializer)?;
+    let mut stats = serde_json::to_value(SecondaryStats::enemy_default()).map_err(D::Error::custom)?;
+    if let Some(fields) = stats.as_object_mut() {
+        fields.extend(listed);
     }
+    serde_json::from_value(stats).map_err(D::Error::custom)
 }
-impl EnemySecondaryStats {
-    pub fn new() -> Self {
-        EnemySecondaryStats {
-            phy_out: 100.0,
-            phy_in: 100.0,
-            mag_out: 100.0,
-            mag_in: 100.0,
-            dot_out: 100.0,
-            dot_in: 100.0,
-            heal_out: 100.0,
-            heal_in: 100.0,
-            all_in: 100.0,
-            all_out: 100.0,
-            crit_chance: 15.0,
-            crit_mod: 200.0,
-            mana_consumption: 100.0,
-            haste: 37.5,
-            dodge: 10.0,
-            hit_chance: 90.0,
-            attack_power: 0.0,
-            spell_power: 0.0,
-            hp: 0,
-            current_hp: 0,
-            mp: 100,
-            current_mp: 100
 
+/// What an enemy skill's base damage is scaled by.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum EnemyScaling {
+    /// The base damage as is.
+    #[default] Flat,
+    /// Base damage plus 10% of the enemy's Attack Power, like the player's AP1.
+    AttackPower,
+    /// Base damage plus 10% of the enemy's Spell Power, like the player's SP1.
+    SpellPower,
+    /// Base damage is a percentage of the player's max HP.
+    PlayerMaxHp,
+}
+
+impl FromStr for EnemyScaling {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Flat" => Ok(EnemyScaling::Flat),
+            "AttackPower" => Ok(EnemyScaling::AttackPower),
+            "SpellPower" => Ok(EnemyScaling::SpellPower),
+            "PlayerMaxHp" => Ok(EnemyScaling::PlayerMaxHp),
+            e => Err(BackendError::InvalidEnemyScaling(format!("Error: {e} ")))
         }
     }
+}
 
-    pub fn add(&mut self, other: &EnemySecondaryStats) {
-        self.phy_out += other.phy_out;
-        self.phy_in += other.phy_in;
-        self.mag_out += other.mag_out;
-        self.mag_in += other.mag_in;
-        self.dot_out += other.dot_out;
-        self.dot_in += other.dot_in;
-        self.heal_out += other.heal_out;
-        self.heal_in += other.heal_in;
-        self.all_in += other.all_in;
-        self.all_out += other.all_out;
-        self.crit_chance += other.crit_chance;
-        self.crit_mod += other.crit_mod;
-        self.mana_consumption += other.mana_consumption;
-        self.haste += other.haste;
-        self.dodge += other.dodge;
-        self.hit_chance += other.hit_chance;
-        self.attack_power += other.attack_power;
-        self.spell_power += other.spell_power;
-        self.hp += other.hp;
-        self.current_hp += other.current_hp;
-        self.mp += other.mp;
-        self.current_mp += other.current_mp;
-    }
-
-    pub fn modify_all_out(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.all_out *= value;
-        } else {
-            self.all_out += value;
+impl EnemyScaling {
+    pub fn compute(&self, damage: f32, enemy: &EnemySecondaryStats, player: &SecondaryStats) -> f32 {
+        match self {
+            EnemyScaling::Flat => damage,
+            EnemyScaling::AttackPower => damage + 0.1 * enemy.attack_power,
+            EnemyScaling::SpellPower => damage + 0.1 * enemy.spell_power,
+            EnemyScaling::PlayerMaxHp => damage / 100.0 * player.hp as f32,
         }
     }
+}
+
+fn default_target() -> Target { Target::Enemy }
+
+/// One enemy attack. Targets are from the enemy's side, so `Target::Enemy` is the player.
+/// A `Type::Heal` skill on `Target::Yourself` heals the enemy instead of hitting the player.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct EnemySkill {
+    pub damage: i32,
+    pub cooldown: u32,
+    #[serde(default)]
+    pub damage_type: Type,
+    #[serde(default)]
+    pub scaling: EnemyScaling,
+    /// `Hit` can't be dodged, `Crit` always crits even when crits are turned off.
+    #[serde(default)]
+    pub force_result: Option<ForceResult>,
+    #[serde(default = "default_target")]
+    pub target: Target,
+}
 
-    pub fn modify_all_in(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.all_in *= value;
-        } else {
-            self.all_in += value;
+impl Default for EnemySkill {
+    fn default() -> Self {
+        Self {
+            damage: 100,
+            cooldown: 2500, // 2 seconds default
+            damage_type: Type::default(),
+            scaling: EnemyScaling::default(),
+            force_result: None,
+            target: Target::Enemy,
         }
     }
+}
 
-    pub fn modify_phy_out(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.phy_out *= value;
-        } else {
-            self.phy_out += value;
-        }
+impl EnemySkill {
+    /// Whether this skill lands on the player. Anything else only heals or buffs the enemy.
+    pub fn hits_player(&self) -> bool {
+        self.target == Target::Enemy && self.damage_type != Type::Heal
     }
 
-    pub fn modify_phy_in(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.phy_in *= value;
-        } else {
-            self.phy_in += value;
-        }
+    /// Damage before any modifier, from the base damage and the scaling source.
+    pub fn base(&self, enemy: &EnemySecondaryStats, player: &SecondaryStats) -> f32 {
+        self.scaling.compute(self.damage as f32, enemy, player)
     }
 
-    pub fn modify_mag_out(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.mag_out *= value;
-        } else {
-            self.mag_out += value;
+    /// The enemy's Out modifiers for this skill's type followed by the player's In modifiers.
+    /// Heals on the enemy see its own Heal Out and Heal In instead.
+    pub fn multipliers(&self, enemy: &EnemySecondaryStats, player: &SecondaryStats, crit: bool) -> Vec<Multiplier> {
+        let crit = crit || self.force_result == Some(ForceResult::Crit);
+        let mut multipliers = self.damage_type.self_multipliers(&StatLayers::from(enemy.clone()), crit);
+        // DoT multipliers never include the crit, add it back for the single hit enemies deal
+        if crit && self.damage_type == Type::DamageOverTime {
+            multipliers.push(Multiplier::new("Crit", enemy.crit_mod / 100.0));
         }
+        let target = if self.hits_player() { player } else { enemy };
+        multipliers.extend(target.incoming_multipliers(&self.damage_type));
+        multipliers
     }
 
-    pub fn modify_mag_in(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.mag_in *= value;
-        } else {
-            self.mag_in += value;
-        }
+    pub fn compute(&self, enemy: &EnemySecondaryStats, player: &SecondaryStats, crit: bool) -> f32 {
+        self.base(enemy, player) * product(&self.multipliers(enemy, player, crit))
     }
 
-    pub fn modify_dot_out(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.dot_out *= value;
-        } else {
-            self.dot_out += value;
+    pub fn crit_chance(&self, enemy: &EnemySecondaryStats, can_crit: bool) -> f32 {
+        if self.force_result == Some(ForceResult::Crit) {
+            return 1.0;
         }
+        if can_crit { (enemy.crit_chance / 100.0).clamp(0.0, 1.0) } else { 0.0 }
     }
 
-    pub fn modify_dot_in(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.dot_in *= value;
-        } else {
-            self.dot_in += value;
+    /// Chance this skill lands on the player. `ForceResult::Hit` and skills that don't
+    /// target the player can't be dodged.
+    pub fn land_chance(&self, enemy: &EnemySecondaryStats, player: &SecondaryStats) -> f32 {
+        if self.force_result == Some(ForceResult::Hit) || !self.hits_player() {
+            return 1.0;
         }
+        land_chance(enemy.hit_chance, player.dodge)
     }
 
-    pub fn modify_heal_out(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.heal_out *= value;
-        } else {
-            self.heal_out += value;
-        }
+    /// Expected damage (or healing on the enemy) of one cast, crit weighted and scaled by the chance to land.
+    pub fn expected(&self, enemy: &EnemySecondaryStats, player: &SecondaryStats, can_crit: bool) -> f32 {
+        let crit = self.crit_chance(enemy, can_crit);
+        let avg = self.compute(enemy, player, false) * (1.0 - crit) + self.compute(enemy, player, true) * crit;
+        avg * self.land_chance(enemy, player)
     }
 
-    pub fn modify_heal_in(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.heal_in *= value;
-        } else {
-            self.heal_in += value;
+    /// Rolls a single cast against the player.
+    pub fn roll(&self, enemy: &EnemySecondaryStats, player: &SecondaryStats, can_crit: bool, rng: &mut Rng) -> f32 {
+        if !rng.chance(self.land_chance(enemy, player)) {
+            return 0.0;
         }
+        let crit = rng.chance(self.crit_chance(enemy, can_crit));
+        self.compute(enemy, player, crit)
     }
+}
+
+/// What moves an enemy into its next phase.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PhaseTrigger {
+    /// Enemy HP at or below this percentage of its max. Never fires against an enemy with infinite HP.
+    HpBelow(f32),
+    /// Seconds since the fight started
+    Time(f32),
+}
 
-    pub fn modify_mana_consumption(&mut self, value: f32, mult: bool) {
-        if mult {
-            self.mana_consumption *= value;
-        } else {
-            self.mana_consumption += value;
+impl PhaseTrigger {
+    /// `hp_percent` is `None` when the enemy has infinite HP.
+    pub fn reached(&self, time: f32, hp_percent: Option<f32>) -> bool {
+        match self {
+            PhaseTrigger::HpBelow(threshold) => hp_percent.is_some_and(|hp| hp <= *threshold),
+            PhaseTrigger::Time(at) => time >= *at,
         }
-        if self.mana_consumption >= 50.0 {
-            self.mana_consumption = 50.0;
+    }
+}
+
+/// A later stage of a fight. The build's own enemy and enemy skills are the opening phase,
+/// phases follow it in order and never go back.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct EnemyPhase {
+    pub name: String,
+    pub trigger: PhaseTrigger,
+    /// Stats that differ from the build's enemy during this phase
+    #[serde(default)]
+    pub overrides: Vec<(StatKey, f32)>,
+    /// The enemy's skills for this phase, they replace the previous phase's
+    #[serde(default)]
+    pub skills: Vec<(EnemySkill, Vec<CustomPassive>, bool)>,
+    /// Seconds the enemy takes no damage after the phase starts
+    #[serde(default)]
+    pub invulnerable: f32,
+}
+
+impl EnemyPhase {
+    /// `base` with this phase's overrides on top.
+    pub fn stats(&self, base: &EnemySecondaryStats) -> EnemySecondaryStats {
+        let mut stats = base.clone();
+        for (key, value) in &self.overrides {
+            stats.set(*key, *value);
         }
+        stats
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn modify_hp(&mut self, value: i32) {
-        self.hp = value;
-        self.current_hp = value;
+    fn skill(damage: i32) -> EnemySkill {
+        EnemySkill { damage, cooldown: 2000, ..Default::default() }
     }
 
-    pub fn take_damage(&mut self, value: i32) {
-        self.current_hp -= value;
+    #[test]
+    fn test_enemy_skill_expected() {
+        let enemy = EnemySecondaryStats { crit_chance: 15.0, ..Default::default() };
+        let player = SecondaryStats { dodge: 30.0, ..Default::default() };
+        let skill = skill(1000);
+        // 90 hit against 30 dodge lands 60% of the time
+        assert!((skill.expected(&enemy, &player, false) - 600.0).abs() < 1e-3);
+        assert_eq!(skill.expected(&enemy, &SecondaryStats { dodge: 95.0, ..Default::default() }, false), 0.0);
+        // 15% crit chance for double damage
+        assert!((skill.expected(&enemy, &player, true) - 690.0).abs() < 1e-2);
     }
 
-    pub fn use_mana(&mut self, value: i32) {
-        self.current_mp -= value;
+    #[test]
+    fn test_enemy_skill_roll() {
+        let enemy = EnemySecondaryStats { crit_chance: 15.0, ..Default::default() };
+        let player = SecondaryStats { dodge: 30.0, ..Default::default() };
+        let skill = skill(1000);
+        let mut rng = Rng::new(3);
+        let rolls: Vec<f32> = (0..1000).map(|_| skill.roll(&enemy, &player, true, &mut rng)).collect();
+        assert!(rolls.iter().all(|d| [0.0, 1000.0, 2000.0].contains(d)));
+        assert!(rolls.contains(&0.0) && rolls.contains(&2000.0));
     }
 
-}
+    #[test]
+    fn test_both_sides_modifiers() {
+        let enemy = EnemySecondaryStats { all_out: 120.0, mag_out: 150.0, hit_chance: 100.0, dodge: 0.0, ..Default::default() };
+        let player = SecondaryStats { phy_in: 50.0, mag_in: 80.0, dodge: 0.0, ..Default::default() };
+        let physical = skill(1000);
+        let magical = EnemySkill { damage_type: Type::Magical, ..skill(1000) };
+        let true_damage = EnemySkill { damage_type: Type::TrueDamage, ..skill(1000) };
+        assert!((physical.expected(&enemy, &player, false) - 1000.0 * 1.2 * 0.5).abs() < 1e-2);
+        assert!((magical.expected(&enemy, &player, false) - 1000.0 * 1.2 * 1.5 * 0.8).abs() < 1e-2);
+        assert_eq!(true_damage.expected(&enemy, &player, false), 1000.0);
+    }
 
+    #[test]
+    fn test_scaling_and_forced_results() {
+        let enemy = EnemySecondaryStats { attack_power: 500.0, crit_chance: 0.0, ..Default::default() };
+        let player = SecondaryStats { hp: 20000, dodge: 90.0, ..Default::default() };
+        let ap = EnemySkill { scaling: EnemyScaling::AttackPower, force_result: Some(ForceResult::Hit), ..skill(100) };
+        assert_eq!(ap.expected(&enemy, &player, false), 150.0);
+        // 90 hit against 90 dodge never lands unless forced
+        let percent = EnemySkill { scaling: EnemyScaling::PlayerMaxHp, ..skill(10) };
+        assert_eq!(percent.expected(&enemy, &player, true), 0.0);
+        let forced = EnemySkill { force_result: Some(ForceResult::Hit), ..percent.clone() };
+        assert_eq!(forced.expected(&enemy, &player, true), 2000.0);
+        let crit = EnemySkill { force_result: Some(ForceResult::Crit), ..percent };
+        assert_eq!(crit.crit_chance(&enemy, false), 1.0);
+    }
 
-#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
-pub struct EnemySkill {
-    pub damage: i32,
-    pub cooldown: u32,
-}
+    #[test]
+    fn test_self_heal_skips_player() {
+        let enemy = EnemySecondaryStats { heal_out: 150.0, heal_in: 50.0, crit_chance: 0.0, ..Default::default() };
+        let player = SecondaryStats { dodge: 100.0, heal_in: 300.0, ..Default::default() };
+        let heal = EnemySkill { damage_type: Type::Heal, target: Target::Yourself, ..skill(1000) };
+        assert!(!heal.hits_player());
+        assert_eq!(heal.expected(&enemy, &player, true), 750.0);
+    }
 
-impl Default for EnemySkill {
-    fn default() -> Self {
-        Self {
-            damage: 100,
-            cooldown: 2500, // 2 seconds default
-        }
+    #[test]
+    fn test_old_skills_load() {
+        let skill: EnemySkill = serde_json::from_str(r#"{"damage": 300, "cooldown": 1500}"#).unwrap();
+        assert_eq!(skill, EnemySkill { damage: 300, cooldown: 1500, ..Default::default() });
+        assert_eq!(EnemyScaling::from_str("SpellPower").unwrap(), EnemyScaling::SpellPower);
+        assert!(EnemyScaling::from_str("Mana").is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_phase_triggers_and_overrides() {
+        assert!(PhaseTrigger::HpBelow(50.0).reached(0.0, Some(50.0)));
+        assert!(!PhaseTrigger::HpBelow(50.0).reached(100.0, None));
+        assert!(PhaseTrigger::Time(30.0).reached(30.0, Some(100.0)));
+        assert!(!PhaseTrigger::Time(30.0).reached(29.9, Some(0.0)));
+
+        let phase = EnemyPhase {
+            name: "Enrage".into(),
+            trigger: PhaseTrigger::HpBelow(30.0),
+            overrides: vec![(StatKey::AllOut, 150.0), (StatKey::PhyIn, 80.0)],
+            skills: vec![],
+            invulnerable: 0.0,
+        };
+        let base = EnemySecondaryStats { crit_chance: 15.0, ..Default::default() };
+        let stats = phase.stats(&base);
+        assert_eq!((stats.all_out, stats.phy_in, stats.crit_chance), (150.0, 80.0, 15.0));
+    }
+}
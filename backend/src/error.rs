@@ -17,6 +17,21 @@ pub enum BackendError {
     #[error("invalid damage type: {0}")]
     InvalidDamageType(String),
 
+    #[error("invalid damage formula: {0}")]
+    InvalidDamageFormula(String),
+
+    #[error("invalid stacking formula: {0}")]
+    InvalidStackingFormula(String),
+
+    #[error("invalid stack gain: {0}")]
+    InvalidStackGain(String),
+
+    #[error("invalid dot refresh rule: {0}")]
+    InvalidDotRefresh(String),
+
+    #[error("invalid enemy scaling: {0}")]
+    InvalidEnemyScaling(String),
+
     #[error("invalid target type: {0}")]
     InvalidTargetType(String),
 
@@ -25,5 +40,19 @@ pub enum BackendError {
 
     #[error("invalid gear slot: {0}")]
     InvalidGearSlot(String),
-}
 
+    #[error("invalid build: {0}")]
+    InvalidBuild(String),
+
+    #[error("invalid stat: {0}")]
+    InvalidStatKey(String),
+
+    #[error("invalid enemy preset: {0}")]
+    InvalidEnemyPreset(String),
+
+    #[error("invalid rotation: {0}")]
+    InvalidRotation(String),
+
+    #[error("invalid sweep: {0}")]
+    InvalidSweep(String),
+}
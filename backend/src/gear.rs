@@ -4,7 +4,7 @@ const MAX_LEVEL: f32 = 100.0;
 const GST_BASE: f32 = 12.0;
 
 use std::str::FromStr;
-use crate::{error::BackendError, player::PrimaryStats};
+use crate::{error::BackendError, passive::{CustomPassive, OperationType, TargetType}, player::PrimaryStats, stat::StatKey};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum Stat {
@@ -58,6 +58,24 @@ pub enum EnhancementPattern {
     Anima,
 }
 
+pub const PATTERNS: [EnhancementPattern; 15] = [
+    EnhancementPattern::Adventurer,
+    EnhancementPattern::Fighter,
+    EnhancementPattern::Thief,
+    EnhancementPattern::Armsman,
+    EnhancementPattern::Hybrid,
+    EnhancementPattern::Wizard,
+    EnhancementPattern::Healer,
+    EnhancementPattern::Spellbreaker,
+    EnhancementPattern::Lucky,
+    EnhancementPattern::Forge,
+    EnhancementPattern::Vim,
+    EnhancementPattern::Hearty,
+    EnhancementPattern::Examen,
+    EnhancementPattern::Pneuma,
+    EnhancementPattern::Anima,
+];
+
 
 #[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Copy, Default)]
 pub enum Trait {
@@ -72,6 +90,17 @@ pub enum Trait {
     Ether,
 }
 
+pub const TRAITS: [Trait; 8] = [
+    Trait::None,
+    Trait::Clairvoyance,
+    Trait::Vainglory,
+    Trait::Absolution,
+    Trait::Penitence,
+    Trait::Lament,
+    Trait::Avarice,
+    Trait::Ether,
+];
+
 impl Trait {
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -85,6 +114,27 @@ impl Trait {
             Trait::Ether => "Ether"
         }
     }
+
+    /// The permanent secondary stat changes the trait gives.
+    pub fn passives(&self) -> Vec<CustomPassive> {
+        let additive = |stat_name: StatKey, value: f32| CustomPassive {
+            target_type: TargetType::Secondary,
+            stat_name,
+            value,
+            operation_type: OperationType::Additive,
+            ..Default::default()
+        };
+        match self {
+            Trait::None => vec![],
+            Trait::Clairvoyance => vec![additive(StatKey::HitChance, 10.0)],
+            Trait::Vainglory => vec![additive(StatKey::AllOut, 15.0), additive(StatKey::HealIn, -50.0)],
+            Trait::Absolution => vec![additive(StatKey::HealOut, 50.0), additive(StatKey::PhyOut, -20.0)],
+            Trait::Penitence => vec![additive(StatKey::AllIn, 25.0), additive(StatKey::DotOut, -25.0)],
+            Trait::Lament => vec![additive(StatKey::CritChance, 20.0), additive(StatKey::Haste, -5.0)],
+            Trait::Avarice => vec![additive(StatKey::Haste, 10.0), additive(StatKey::AllIn, -35.0)],
+            Trait::Ether => vec![additive(StatKey::ManaConsumption, -33.0)],
+        }
+    }
 }
 
 #[allow(clippy::should_implement_trait)]
@@ -309,7 +359,6 @@ pub fn get_stats(enh: &Enhancement, slot: GearSlot) -> PrimaryStats {
     let gear_stat_total = (gst_total_val * slot.ratio()).round() as i32;
 
     let ratios = enh.pattern.ratios();
-    println!("STR: {}\nGST: {}\nRATIO: {}", gear_stat_total as f32 * ratios.str_ratio, gear_stat_total as f32, ratios.str_ratio);
     let mut stats = StatBlock {
         str_stat: (gear_stat_total as f32 * ratios.str_ratio).round() as i32,
         dex_stat: (gear_stat_total as f32 * ratios.dex_ratio).round() as i32,
@@ -321,7 +370,6 @@ pub fn get_stats(enh: &Enhancement, slot: GearSlot) -> PrimaryStats {
     
 
     let mut other_count = stats.sum();
-    println!("Other count: {}", other_count);
     let mut index = 0;
     while other_count < gear_stat_total {
         match STATS_ORDER[index] {
@@ -361,7 +409,7 @@ mod tests {
         assert_eq!(GearSlot::from_str("Armor").unwrap(), GearSlot::Armor);
         assert_eq!(GearSlot::from_str("BA").unwrap(), GearSlot::Cape);
         assert_eq!(GearSlot::from_str("weapon").unwrap(), GearSlot::Weapon);
-        assert!(GearSlot::from_str("unknown").is_none());
+        assert!(GearSlot::from_str("unknown").is_err());
     }
 
     #[test]
@@ -3,6 +3,18 @@ pub mod error;
 pub mod gear;
 pub mod damage;
 pub mod enemy;
+pub mod monte_carlo;
+pub mod breakdown;
+pub mod passive;
+pub mod sim;
+pub mod build;
+pub mod stat;
+pub mod preset;
+pub mod survival;
+pub mod rotation;
+pub mod optimizer;
+pub mod weights;
+pub mod sweep;
 
 
 #[cfg(test)]
@@ -0,0 +1,165 @@
+use serde::{Serialize, Deserialize};
+
+/// Small seedable RNG (SplitMix64) so fights can be replayed from a seed.
+/// Doesn't need OS randomness, which keeps it working under wasm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform float in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        // Top 24 bits fit exactly in an f32 mantissa
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Rolls `true` with probability `p` (0.0 to 1.0).
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.next_f32() < p
+    }
+}
+
+/// Mean, spread and percentiles of a set of samples.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Summary {
+    pub mean: f32,
+    pub std_dev: f32,
+    pub p5: f32,
+    pub p50: f32,
+    pub p95: f32,
+}
+
+impl Summary {
+    /// Returns `None` when there are no samples.
+    pub fn from_samples(samples: &[f32]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let n = samples.len() as f32;
+        let mean = samples.iter().sum::<f32>() / n;
+        let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / n;
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        Some(Summary {
+            mean,
+            std_dev: variance.sqrt(),
+            p5: percentile(&sorted, 0.05),
+            p50: percentile(&sorted, 0.50),
+            p95: percentile(&sorted, 0.95),
+        })
+    }
+}
+
+// Nearest rank on an already sorted slice
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    let idx = (p * (sorted.len() - 1) as f32).round() as usize;
+    sorted[idx.min(sorted.len() - 1)]
+}
+
+/// Result of running the same fight many times with rolled crits, hits and dodges.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MonteCarloReport {
+    pub seed: u64,
+    pub iterations: usize,
+    pub dps: Summary,
+    /// Only covers iterations where the enemy died, `None` if it never did.
+    pub time_to_kill: Option<Summary>,
+    /// Fraction of iterations the enemy died in.
+    pub kill_rate: f32,
+}
+
+impl MonteCarloReport {
+    /// Runs `fight` `iterations` times off one RNG seeded with `seed`.
+    /// `fight` returns the DPS of that run and the time the enemy died at, if it did.
+    pub fn run<F>(seed: u64, iterations: usize, mut fight: F) -> Self
+    where
+        F: FnMut(&mut Rng) -> (f32, Option<f32>),
+    {
+        let mut rng = Rng::new(seed);
+        let mut dps = Vec::with_capacity(iterations);
+        let mut kills = Vec::new();
+        for _ in 0..iterations {
+            let (run_dps, kill_time) = fight(&mut rng);
+            dps.push(run_dps);
+            if let Some(t) = kill_time {
+                kills.push(t);
+            }
+        }
+
+        MonteCarloReport {
+            seed,
+            iterations,
+            dps: Summary::from_samples(&dps).unwrap_or_default(),
+            time_to_kill: Summary::from_samples(&kills),
+            kill_rate: if iterations > 0 { kills.len() as f32 / iterations as f32 } else { 0.0 },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_is_deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+        let run_a: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        let run_b: Vec<u64> = (0..10).map(|_| b.next_u64()).collect();
+        let run_c: Vec<u64> = (0..10).map(|_| c.next_u64()).collect();
+        assert_eq!(run_a, run_b);
+        assert_ne!(run_a, run_c);
+    }
+
+    #[test]
+    fn test_rng_chance() {
+        let mut rng = Rng::new(7);
+        let hits = (0..10_000).filter(|_| rng.chance(0.3)).count();
+        assert!((2_800..3_200).contains(&hits));
+        assert!(!rng.chance(0.0));
+        assert!(rng.chance(1.0));
+    }
+
+    #[test]
+    fn test_summary_from_samples() {
+        let samples: Vec<f32> = (1..=100).map(|i| i as f32).collect();
+        let summary = Summary::from_samples(&samples).unwrap();
+        assert_eq!(summary.mean, 50.5);
+        assert_eq!(summary.p50, 51.0);
+        assert_eq!(summary.p5, 6.0);
+        assert_eq!(summary.p95, 95.0);
+        assert!((summary.std_dev - 28.866).abs() < 1e-2);
+        assert!(Summary::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn test_report_same_seed_same_result() {
+        let fight = |rng: &mut Rng| {
+            let dps = 1000.0 + rng.next_f32() * 200.0;
+            let kill = if rng.chance(0.5) { Some(30.0 + rng.next_f32() * 5.0) } else { None };
+            (dps, kill)
+        };
+        let a = MonteCarloReport::run(9, 200, fight);
+        let b = MonteCarloReport::run(9, 200, fight);
+        assert_eq!(a, b);
+        assert!(a.kill_rate > 0.3 && a.kill_rate < 0.7);
+        assert!(a.time_to_kill.is_some());
+    }
+}
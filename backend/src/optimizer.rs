@@ -0,0 +1,422 @@
+use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use crate::{
+    build::{Build, Equipment},
+    gear::{Enhancement, GearSlot, PATTERNS, TRAITS},
+    sim::{FightSettings, Loadout, RotationAction, Simulation},
+    stat::{StatBlock, StatKey, capped},
+    survival::min_effective_hp,
+};
+
+// --- Rotation search ---
+
+/// How hard the rotation optimizer looks.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct RotationSearch {
+    /// Longest cycle tried, every shorter one is tried too
+    pub max_length: usize,
+    /// How many rotations come back
+    pub top_n: usize,
+    /// Up to this many candidates every cycle is simulated, past it a beam search takes over
+    pub exhaustive_limit: usize,
+    /// Partial cycles the beam search keeps after each step
+    pub beam_width: usize,
+}
+
+impl Default for RotationSearch {
+    fn default() -> Self {
+        RotationSearch { max_length: 4, top_n: 5, exhaustive_limit: 1000, beam_width: 8 }
+    }
+}
+
+/// A rotation the optimizer tried and how it did.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RankedRotation {
+    pub rotation: Vec<RotationAction>,
+    pub dps: f32,
+    /// Seconds to kill the enemy, `None` if it survived or has infinite HP
+    pub time_to_kill: Option<f32>,
+}
+
+/// Skills worth putting in a rotation. The auto attack only counts when it isn't running on its own.
+fn candidate_skills(build: &dyn Loadout, settings: &FightSettings) -> Vec<usize> {
+    let first = if settings.auto_attack { 1 } else { 0 };
+    (first..build.skills().len()).collect()
+}
+
+fn evaluate(build: &dyn Loadout, settings: &FightSettings, skills: &[usize]) -> RankedRotation {
+    let rotation: Vec<RotationAction> = skills.iter().map(|&s| RotationAction::Skill(s)).collect();
+    let result = Simulation::new(build, &rotation, settings.clone()).run(None);
+    RankedRotation {
+        rotation,
+        dps: result.dps,
+        time_to_kill: result.enemy_defeated.then_some(result.time),
+    }
+}
+
+/// Best first: highest DPS, then the fastest kill.
+fn rank(rotations: &mut [RankedRotation]) {
+    rotations.sort_by(|a, b| {
+        b.dps.total_cmp(&a.dps)
+            .then_with(|| a.time_to_kill.unwrap_or(f32::INFINITY).total_cmp(&b.time_to_kill.unwrap_or(f32::INFINITY)))
+    });
+}
+
+/// One form per distinct loop: `[3, 3]` becomes `[3]` and `[2, 1]` becomes `[1, 2]`,
+/// the shortest repeating period turned to its lexicographically smallest start.
+fn canonical(cycle: &[usize]) -> Vec<usize> {
+    let period = (1..=cycle.len())
+        .find(|&p| cycle.len().is_multiple_of(p) && cycle.iter().enumerate().all(|(i, s)| *s == cycle[i % p]))
+        .unwrap_or(cycle.len());
+    let period = &cycle[..period];
+    (0..period.len())
+        .map(|start| [&period[start..], &period[..start]].concat())
+        .min()
+        .unwrap_or_default()
+}
+
+/// Every distinct cycle of 1 to `max_length` skills, in canonical form.
+fn all_cycles(candidates: &[usize], max_length: usize) -> Vec<Vec<usize>> {
+    let mut cycles: Vec<Vec<usize>> = vec![];
+    let mut seen: HashSet<Vec<usize>> = HashSet::new();
+    let mut previous: Vec<Vec<usize>> = vec![vec![]];
+    for _ in 0..max_length {
+        previous = previous.iter()
+            .flat_map(|prefix| candidates.iter().map(move |&s| [prefix.as_slice(), &[s]].concat()))
+            .collect();
+        cycles.extend(previous.iter().map(|c| canonical(c)).filter(|c| seen.insert(c.clone())));
+    }
+    cycles
+}
+
+/// Grows cycles one skill at a time, keeping only the `beam_width` best after each step.
+fn beam_search(build: &dyn Loadout, settings: &FightSettings, candidates: &[usize], search: &RotationSearch) -> Vec<RankedRotation> {
+    let mut tried: Vec<RankedRotation> = vec![];
+    let mut seen: HashSet<Vec<usize>> = HashSet::new();
+    let mut beam: Vec<Vec<usize>> = vec![vec![]];
+    for _ in 0..search.max_length {
+        let mut step: Vec<RankedRotation> = beam.iter()
+            .flat_map(|prefix| candidates.iter().map(move |&s| canonical(&[prefix.as_slice(), &[s]].concat())))
+            .filter(|cycle| seen.insert(cycle.clone()))
+            .map(|cycle| evaluate(build, settings, &cycle))
+            .collect();
+        rank(&mut step);
+        beam = step.iter().take(search.beam_width.max(1)).map(|r| skill_indices(&r.rotation)).collect();
+        tried.extend(step);
+    }
+    tried
+}
+
+fn skill_indices(rotation: &[RotationAction]) -> Vec<usize> {
+    rotation.iter().filter_map(|a| match a { RotationAction::Skill(s) => Some(*s), RotationAction::Delay(_) => None }).collect()
+}
+
+/// The `top_n` best rotations for `build` in a fight set up by `settings`.
+/// Short searches try every cycle, longer ones fall back to a beam search.
+pub fn optimize_rotation(build: &dyn Loadout, settings: &FightSettings, search: &RotationSearch) -> Vec<RankedRotation> {
+    let candidates = candidate_skills(build, settings);
+    if candidates.is_empty() || search.max_length == 0 {
+        return vec![];
+    }
+    let total: usize = (1..=search.max_length as u32).map(|l| candidates.len().saturating_pow(l)).fold(0, usize::saturating_add);
+    let mut ranked = if total <= search.exhaustive_limit {
+        all_cycles(&candidates, search.max_length).iter().map(|c| evaluate(build, settings, c)).collect()
+    } else {
+        beam_search(build, settings, &candidates, search)
+    };
+    rank(&mut ranked);
+    ranked.truncate(search.top_n);
+    ranked
+}
+
+// --- Enhancement search ---
+
+/// What the enhancement optimizer tries to improve.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LoadoutMetric {
+    /// DPS over the whole fight
+    #[default]
+    SustainedDps,
+    /// DPS over the opening seconds only
+    Burst,
+    TimeToKill,
+    /// The lower of physical and magical effective HP
+    EffectiveHp,
+}
+
+impl LoadoutMetric {
+    pub const ALL: [LoadoutMetric; 4] = [LoadoutMetric::SustainedDps, LoadoutMetric::Burst, LoadoutMetric::TimeToKill, LoadoutMetric::EffectiveHp];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            LoadoutMetric::SustainedDps => "Sustained DPS",
+            LoadoutMetric::Burst => "Burst DPS",
+            LoadoutMetric::TimeToKill => "Time to Kill",
+            LoadoutMetric::EffectiveHp => "Effective HP",
+        }
+    }
+
+    /// Whether `a` beats `b`, only time to kill wants the lower value
+    fn better(&self, a: f32, b: f32) -> std::cmp::Ordering {
+        match self {
+            LoadoutMetric::TimeToKill => a.total_cmp(&b),
+            _ => b.total_cmp(&a),
+        }
+    }
+}
+
+/// How the enhancement optimizer searches.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct EnhancementSearch {
+    pub metric: LoadoutMetric,
+    /// Seconds of fight `Burst` looks at
+    pub burst_window: f32,
+    /// Best pattern and trait picks per slot kept for the combined search
+    pub per_slot: usize,
+    /// How many loadouts come back
+    pub top_n: usize,
+}
+
+impl Default for EnhancementSearch {
+    fn default() -> Self {
+        EnhancementSearch { metric: LoadoutMetric::SustainedDps, burst_window: 10.0, per_slot: 3, top_n: 5 }
+    }
+}
+
+/// A set of gear the optimizer tried, compared to the build it started from.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RankedLoadout {
+    pub equipment: Equipment,
+    /// The metric's own value: DPS, seconds to kill (∞ without a kill) or effective HP
+    pub value: f32,
+    pub value_diff: f32,
+    /// DPS of the fight the metric looked at, the opening seconds for `Burst`
+    pub dps: f32,
+    pub dps_diff: f32,
+    /// Every primary and secondary stat that changed
+    pub stat_diff: Vec<(StatKey, f32)>,
+    /// Slots whose pattern or trait changed
+    pub changed: Vec<GearSlot>,
+}
+
+impl RankedLoadout {
+    /// Puts this gear on `build`, traits included.
+    pub fn apply(&self, build: &mut Build) {
+        build.set_equipment(self.equipment.clone());
+    }
+}
+
+struct Evaluated {
+    build: Build,
+    value: f32,
+    dps: f32,
+}
+
+fn evaluate_loadout(base: &Build, equipment: &Equipment, settings: &FightSettings, rotation: &[RotationAction], search: &EnhancementSearch) -> Evaluated {
+    let mut build = base.clone();
+    build.set_equipment(equipment.clone());
+    let fight = match search.metric {
+        LoadoutMetric::Burst => FightSettings { duration: search.burst_window.min(settings.duration), ..settings.clone() },
+        _ => settings.clone(),
+    };
+    let result = Simulation::new(&build, rotation, fight).run(None);
+    let value = match search.metric {
+        LoadoutMetric::SustainedDps | LoadoutMetric::Burst => result.dps,
+        LoadoutMetric::TimeToKill => if result.enemy_defeated { result.time } else { f32::INFINITY },
+        LoadoutMetric::EffectiveHp => min_effective_hp(&build.stat_layers(&[]).dynamic_stats, &capped(&build.enemy)),
+    };
+    Evaluated { build, value, dps: result.dps }
+}
+
+fn rank_loadouts(metric: LoadoutMetric, loadouts: &mut [Evaluated]) {
+    loadouts.sort_by(|a, b| metric.better(a.value, b.value).then_with(|| b.dps.total_cmp(&a.dps)));
+}
+
+/// Every pattern and trait in `slot` with the rest of the gear left alone, best first.
+/// Picks that score the same as a better listed one are dropped, they'd only repeat it.
+fn slot_shortlist(build: &Build, slot: GearSlot, settings: &FightSettings, rotation: &[RotationAction], search: &EnhancementSearch) -> Vec<Enhancement> {
+    let current = build.equipment.slot(slot);
+    let mut options: Vec<(Enhancement, Evaluated)> = PATTERNS.iter()
+        .flat_map(|&pattern| TRAITS.iter().map(move |&r#trait| Enhancement { pattern, r#trait, ..current.clone() }))
+        .map(|enhancement| {
+            let mut equipment = build.equipment.clone();
+            *equipment.slot_mut(slot) = enhancement.clone();
+            let evaluated = evaluate_loadout(build, &equipment, settings, rotation, search);
+            (enhancement, evaluated)
+        })
+        .collect();
+    options.sort_by(|(_, a), (_, b)| search.metric.better(a.value, b.value).then_with(|| b.dps.total_cmp(&a.dps)));
+    options.dedup_by(|(_, a), (_, b)| a.value == b.value && a.dps == b.dps);
+    options.into_iter().take(search.per_slot.max(1)).map(|(enhancement, _)| enhancement).collect()
+}
+
+fn stat_diff(from: &Build, to: &Build) -> Vec<(StatKey, f32)> {
+    StatKey::iter()
+        .filter_map(|key| {
+            let get = |build: &Build| if key.is_primary() { build.primary_stats.get(key) } else { build.secondary_stats.get(key) };
+            let diff = get(to)? - get(from)?;
+            (diff.abs() > 1e-3).then_some((key, diff))
+        })
+        .collect()
+}
+
+/// The `top_n` best gear setups for `build` by `search.metric`, fighting with `rotation`.
+///
+/// Each slot is searched on its own first, every pattern and trait against the current
+/// gear in the other slots. Only the best `per_slot` picks of each slot go on to the
+/// combined search, which tries every mix of them and the current gear.
+pub fn optimize_enhancements(build: &Build, settings: &FightSettings, rotation: &[RotationAction], search: &EnhancementSearch) -> Vec<RankedLoadout> {
+    let current = evaluate_loadout(build, &build.equipment, settings, rotation, search);
+    let shortlists: Vec<Vec<Enhancement>> = Equipment::SLOTS.iter()
+        .map(|&slot| slot_shortlist(build, slot, settings, rotation, search))
+        .collect();
+
+    let mut mixes: Vec<Equipment> = vec![build.equipment.clone()];
+    for (&slot, shortlist) in Equipment::SLOTS.iter().zip(&shortlists) {
+        mixes = mixes.iter()
+            .flat_map(|equipment| shortlist.iter().map(move |enhancement| {
+                let mut equipment = equipment.clone();
+                *equipment.slot_mut(slot) = enhancement.clone();
+                equipment
+            }))
+            .collect();
+    }
+    // Picks that are best on their own can still clash (a stat over its cap), the current gear stays in the running
+    if !mixes.contains(&build.equipment) {
+        mixes.push(build.equipment.clone());
+    }
+
+    let mut evaluated: Vec<Evaluated> = mixes.iter().map(|equipment| evaluate_loadout(build, equipment, settings, rotation, search)).collect();
+    rank_loadouts(search.metric, &mut evaluated);
+    evaluated.into_iter()
+        .take(search.top_n)
+        .map(|e| RankedLoadout {
+            stat_diff: stat_diff(&current.build, &e.build),
+            changed: Equipment::SLOTS.iter().copied().filter(|&slot| e.build.equipment.slot(slot) != build.equipment.slot(slot)).collect(),
+            value_diff: e.value - current.value,
+            dps_diff: e.dps - current.dps,
+            value: e.value,
+            dps: e.dps,
+            equipment: e.build.equipment,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gear::{EnhancementPattern, Trait};
+
+    fn fight() -> FightSettings {
+        FightSettings { duration: 30.0, ..Default::default() }
+    }
+
+    #[test]
+    fn test_all_cycles() {
+        let cycles = all_cycles(&[1, 2], 3);
+        // [1] [2] [1 2] [1 1 2] [1 2 2], repeats and rotations of those are dropped
+        assert_eq!(cycles.len(), 5);
+        assert!(cycles.contains(&vec![1, 1, 2]));
+        assert!(!cycles.contains(&vec![2, 1, 1]) && !cycles.contains(&vec![1, 1]));
+    }
+
+    #[test]
+    fn test_canonical() {
+        assert_eq!(canonical(&[3, 3]), vec![3]);
+        assert_eq!(canonical(&[2, 1]), vec![1, 2]);
+        assert_eq!(canonical(&[2, 1, 2, 1]), vec![1, 2]);
+        assert_eq!(canonical(&[3, 1, 2]), vec![1, 2, 3]);
+        assert_eq!(canonical(&[1, 1, 2]), vec![1, 1, 2]);
+    }
+
+    #[test]
+    fn test_top_rotations_are_distinct() {
+        let mut build = Build::default();
+        for (i, (skill, _, _)) in build.skills.iter_mut().enumerate().skip(1) {
+            skill.mp = 0;
+            skill.cd = 0;
+            skill.damage = if i == 3 { 5.0 } else { 1.0 };
+        }
+        for exhaustive_limit in [1000, 0] {
+            let search = RotationSearch { max_length: 4, top_n: 10, exhaustive_limit, ..Default::default() };
+            let ranked = optimize_rotation(&build, &fight(), &search);
+            let loops: HashSet<Vec<usize>> = ranked.iter().map(|r| canonical(&skill_indices(&r.rotation))).collect();
+            assert_eq!(loops.len(), ranked.len());
+        }
+    }
+
+    #[test]
+    fn test_exhaustive_finds_the_big_hitter() {
+        let mut build = Build::default();
+        // The auto attack (skill 0) keeps its own timer
+        for (i, (skill, _, _)) in build.skills.iter_mut().enumerate().skip(1) {
+            skill.mp = 0;
+            skill.cd = 0;
+            skill.damage = if i == 3 { 5.0 } else { 1.0 };
+        }
+        let search = RotationSearch { max_length: 2, top_n: 3, ..Default::default() };
+        let ranked = optimize_rotation(&build, &fight(), &search);
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(ranked[0].rotation, vec![RotationAction::Skill(3)]);
+        assert!(ranked.windows(2).all(|w| w[0].dps >= w[1].dps));
+    }
+
+    #[test]
+    fn test_beam_search_matches_exhaustive_on_easy_builds() {
+        let mut build = Build::default();
+        for (i, (skill, _, _)) in build.skills.iter_mut().enumerate().skip(1) {
+            skill.mp = 0;
+            skill.cd = if i == 2 { 6000 } else { 0 };
+            skill.damage = [1.0, 1.0, 8.0, 2.0, 1.0][i];
+        }
+        let exhaustive = optimize_rotation(&build, &fight(), &RotationSearch { max_length: 3, ..Default::default() });
+        let beam = optimize_rotation(&build, &fight(), &RotationSearch { max_length: 3, exhaustive_limit: 0, ..Default::default() });
+        assert!((exhaustive[0].dps - beam[0].dps).abs() < 1e-3);
+        assert!(skill_indices(&beam[0].rotation).contains(&2));
+    }
+
+    #[test]
+    fn test_time_to_kill_reported() {
+        let settings = FightSettings { enemy_hp: 1000, ..fight() };
+        let ranked = optimize_rotation(&Build::default(), &settings, &RotationSearch { max_length: 1, ..Default::default() });
+        assert!(ranked.iter().any(|r| r.time_to_kill.is_some()));
+    }
+
+    fn quick_search(metric: LoadoutMetric) -> EnhancementSearch {
+        EnhancementSearch { metric, per_slot: 2, top_n: 3, ..Default::default() }
+    }
+
+    #[test]
+    fn test_enhancements_ranked_and_diffed() {
+        let build = Build::default();
+        let rotation = [RotationAction::Skill(1)];
+        let settings = FightSettings { duration: 10.0, ..Default::default() };
+        let ranked = optimize_enhancements(&build, &settings, &rotation, &quick_search(LoadoutMetric::SustainedDps));
+        assert_eq!(ranked.len(), 3);
+        assert!(ranked.windows(2).all(|w| w[0].value >= w[1].value));
+        // The search always has the current gear to fall back on
+        assert!(ranked[0].value_diff >= 0.0);
+
+        let best = &ranked[0];
+        let mut applied = build.clone();
+        best.apply(&mut applied);
+        assert_eq!(applied.equipment, best.equipment);
+        for (key, diff) in &best.stat_diff {
+            let get = |b: &Build| if key.is_primary() { b.primary_stats.get(*key) } else { b.secondary_stats.get(*key) };
+            assert!((get(&applied).unwrap() - get(&build).unwrap() - diff).abs() < 1e-3);
+        }
+        assert!(best.changed.iter().all(|&slot| applied.equipment.slot(slot) != build.equipment.slot(slot)));
+    }
+
+    #[test]
+    fn test_effective_hp_prefers_endurance() {
+        let build = Build::default();
+        let settings = FightSettings { duration: 1.0, ..Default::default() };
+        let ranked = optimize_enhancements(&build, &settings, &[], &quick_search(LoadoutMetric::EffectiveHp));
+        assert!(ranked[0].value_diff > 0.0);
+        assert!(Equipment::SLOTS.iter().any(|&slot| ranked[0].equipment.slot(slot).pattern == EnhancementPattern::Hearty));
+        // Penitence's All In is the only trait that lowers damage taken
+        assert!(Equipment::SLOTS.iter().any(|&slot| ranked[0].equipment.slot(slot).r#trait == Trait::Penitence));
+    }
+}
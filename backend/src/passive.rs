@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use crate::stat::{ModifierLayer, StatBlock, StatDerivation, StatKey};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub enum TargetType {
+    Primary,
+    Secondary,
+    Enemy,
+}
+
+/// Which layer a passive goes in, see [`ModifierLayer`] for the order they're applied in.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub enum OperationType {
+    Multiplicative,
+    Additive,
+    /// Added after every multiplier
+    Final,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct CustomPassive {
+    pub target_type: TargetType,
+    pub stat_name: StatKey,
+    pub value: f32,
+    pub operation_type: OperationType,
+    pub duration: Option<u32>,
+}
+
+impl Default for CustomPassive {
+    fn default() -> Self {
+        CustomPassive {
+            target_type: TargetType::Primary,
+            stat_name: StatKey::Strength,
+            value: 0.0,
+            operation_type: OperationType::Additive,
+            duration: None
+        }
+    }
+}
+
+impl CustomPassive {
+    pub fn layer(&self) -> ModifierLayer {
+        match self.operation_type {
+            OperationType::Additive => ModifierLayer::Additive,
+            OperationType::Multiplicative => ModifierLayer::Multiplicative,
+            OperationType::Final => ModifierLayer::Final,
+        }
+    }
+
+    /// Whether a positive flat amount lowers the stat, see `signed_value`.
+    pub fn is_resistance(target_type: &TargetType, stat_name: StatKey) -> bool {
+        *target_type == TargetType::Secondary
+            && matches!(stat_name, StatKey::AllIn | StatKey::PhyIn | StatKey::MagIn | StatKey::DotIn)
+    }
+
+    /// A permanent flat passive that moves the stat's field by `amount`, resistances included.
+    pub fn raising(target_type: TargetType, stat_name: StatKey, amount: f32) -> Self {
+        let value = if Self::is_resistance(&target_type, stat_name) { -amount } else { amount };
+        CustomPassive { target_type, stat_name, value, operation_type: OperationType::Additive, duration: None }
+    }
+
+    /// What this passive adds to (or multiplies) its stat.
+    ///
+    /// Sign convention: a positive amount raises the stat, except on the player's damage
+    /// taken stats (All, Phy, Mag and DoT In). Those are resistances, so +20 Phy In on the
+    /// player means 20% less physical damage taken and lowers `phy_in`. An enemy debuff of
+    /// +20 Phy In raises the enemy's `phy_in`, so it takes 20% more. Heal In is healing
+    /// received and is never flipped, -50 Heal In halves it. Multipliers are factors and
+    /// are never flipped either. Saves from before Heal In stopped flipping are migrated
+    /// when loaded, see `Build::VERSION`.
+    pub fn signed_value(&self) -> f32 {
+        let resistance = Self::is_resistance(&self.target_type, self.stat_name);
+        match self.operation_type {
+            OperationType::Additive | OperationType::Final if resistance => -self.value,
+            _ => self.value,
+        }
+    }
+}
+
+/// Sorts the passives aimed at `target` into layers for every stat `stats` has.
+/// Stats nothing touches come back with no modifiers.
+pub fn derive_stats<'a, S: StatBlock>(stats: &S, target: TargetType, passives: impl IntoIterator<Item = &'a CustomPassive>) -> Vec<StatDerivation> {
+    let passives: Vec<&CustomPassive> = passives.into_iter().filter(|p| p.target_type == target).collect();
+    StatKey::iter()
+        .filter_map(|key| {
+            let mut derivation = StatDerivation::new(key, stats.get(key)?);
+            for passive in passives.iter().filter(|p| p.stat_name == key) {
+                derivation.push(passive.layer(), passive.signed_value());
+            }
+            Some(derivation)
+        })
+        .collect()
+}
+
+/// `stats` with every passive aimed at `target` applied layer by layer,
+/// so the result doesn't depend on the order of `passives`.
+pub fn apply_passives<'a, S: StatBlock + Clone>(stats: &S, target: TargetType, passives: impl IntoIterator<Item = &'a CustomPassive>) -> S {
+    let mut result = stats.clone();
+    for derivation in derive_stats(stats, target, passives).iter().filter(|d| d.is_modified()) {
+        result.set(derivation.key, derivation.value());
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{enemy::EnemySecondaryStats, player::{PrimaryStats, SecondaryStats}};
+
+    fn passive(target_type: TargetType, stat_name: StatKey, value: f32, operation_type: OperationType) -> CustomPassive {
+        CustomPassive { target_type, stat_name, value, operation_type, duration: None }
+    }
+
+    #[test]
+    fn test_player_in_stats_go_down() {
+        let stats = apply_passives(&SecondaryStats::default(), TargetType::Secondary, &[passive(TargetType::Secondary, StatKey::PhyIn, 20.0, OperationType::Additive)]);
+        assert_eq!(stats.phy_in, 80.0);
+    }
+
+    #[test]
+    fn test_player_heal_in_is_not_flipped() {
+        let stats = apply_passives(&SecondaryStats::default(), TargetType::Secondary, &[passive(TargetType::Secondary, StatKey::HealIn, -50.0, OperationType::Additive)]);
+        assert_eq!(stats.heal_in, 50.0);
+    }
+
+    #[test]
+    fn test_raising_moves_the_field() {
+        let raised = apply_passives(&SecondaryStats::default(), TargetType::Secondary, &[
+            CustomPassive::raising(TargetType::Secondary, StatKey::PhyIn, 10.0),
+            CustomPassive::raising(TargetType::Secondary, StatKey::Haste, 10.0),
+        ]);
+        assert_eq!(raised.phy_in, 110.0);
+        assert_eq!(raised.haste, SecondaryStats::default().haste + 10.0);
+    }
+
+    #[test]
+    fn test_enemy_debuff_and_wrong_target() {
+        let enemy = EnemySecondaryStats::default();
+        let debuffed = apply_passives(&enemy, TargetType::Enemy, &[passive(TargetType::Enemy, StatKey::PhyIn, 25.0, OperationType::Additive)]);
+        assert_eq!(debuffed.phy_in, enemy.phy_in + 25.0);
+
+        let primary = PrimaryStats::new(100, 0, 0, 0, 0, 0);
+        let primary = apply_passives(&primary, TargetType::Primary, &[passive(TargetType::Secondary, StatKey::Strength, 2.0, OperationType::Multiplicative)]);
+        assert_eq!(primary.strength, 100);
+    }
+
+    #[test]
+    fn test_layers_ignore_passive_order() {
+        let add = passive(TargetType::Secondary, StatKey::AllOut, 20.0, OperationType::Additive);
+        let mult = passive(TargetType::Secondary, StatKey::AllOut, 1.5, OperationType::Multiplicative);
+        let flat = passive(TargetType::Secondary, StatKey::AllOut, 5.0, OperationType::Final);
+        let base = SecondaryStats::default();
+
+        let forward = apply_passives(&base, TargetType::Secondary, [&add, &mult, &flat]);
+        let backward = apply_passives(&base, TargetType::Secondary, [&flat, &mult, &add]);
+        assert_eq!(forward.all_out, (100.0 + 20.0) * 1.5 + 5.0);
+        assert_eq!(forward, backward);
+    }
+
+    #[test]
+    fn test_derivation_text() {
+        let passives = [
+            passive(TargetType::Secondary, StatKey::Haste, 1.2, OperationType::Multiplicative),
+            passive(TargetType::Secondary, StatKey::Haste, 30.0, OperationType::Additive),
+        ];
+        let stats = SecondaryStats { haste: 20.0, ..Default::default() };
+        let derivations = derive_stats(&stats, TargetType::Secondary, &passives);
+        let haste = derivations.iter().find(|d| d.key == StatKey::Haste).unwrap();
+        assert_eq!(haste.to_string(), "Haste: 20.00 + 30.00 = 50.00 × 1.20 = 60.00");
+        let crit = derivations.iter().find(|d| d.key == StatKey::CritChance).unwrap();
+        assert_eq!(crit.to_string(), "Crit Chance: 5.00");
+    }
+}
@@ -2,6 +2,7 @@
 use std::fmt::Display;
 use std::str::FromStr;
 use serde::{Deserialize, Serialize};
+use crate::damage::{Multiplier, Type, product};
 use crate::error::BackendError;
 
 pub const MAX_LEVEL: u32 = 100;pub const X_FACTOR: u32 = 1640;
@@ -84,7 +85,6 @@ impl ClassModel {
 
     pub fn level_primary_stat_total(&self, player: &Player) -> PrimaryStats {
         let list = self.level_interstat_total(player);
-        println!("LIST as F32: {}", (list as f32 * 0.24).round() as i32 );
         match self {
             ClassModel::TankMelee => PrimaryStats {
                 strength: (list as f32 * 0.27).round() as i32,
@@ -481,7 +481,6 @@ impl ClassModel {
     }
     pub fn secondary_stats_convert(&self, player: &Player, primary: &PrimaryStats) -> SecondaryStats {
         let hp = player.base_hp() + (primary.endurance * 5);
-        println!("BASE HP: {}", hp);
         SecondaryStats {
             phy_out: self.phy_out_convert(),
             phy_in: self.phy_in_convert(),
@@ -587,6 +586,7 @@ impl PrimaryStats {
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
 pub struct SecondaryStats {
     pub phy_out: f32,
     pub phy_in: f32,
@@ -627,7 +627,7 @@ impl Default for SecondaryStats {
             all_out: 100.0,
             crit_chance: 5.0,
             crit_mod: 200.0,
-            mana_consumption: 0.0,
+            mana_consumption: 100.0,
             haste: 0.0,
             dodge: 4.0,
             hit_chance: 90.0,
@@ -754,9 +754,6 @@ impl SecondaryStats {
         } else {
             self.mana_consumption += value;
         }
-        if self.mana_consumption >= 50.0 {
-            self.mana_consumption = 50.0;
-        }
     }
 
     pub fn modify_hp(&mut self, value: i32) {
@@ -772,9 +769,61 @@ impl SecondaryStats {
         self.current_mp -= value;
     }
 
+    /// Incoming modifiers for a hit of `damage_type`, one by one.
+    /// True damage ignores them, heals only see Heal In.
+    pub fn incoming_multipliers(&self, damage_type: &Type) -> Vec<Multiplier> {
+        let all_in = Multiplier::new("All In", self.all_in / 100.0);
+        match damage_type {
+            Type::Physical => vec![all_in, Multiplier::new("Phy In", self.phy_in / 100.0)],
+            Type::Magical => vec![all_in, Multiplier::new("Mag In", self.mag_in / 100.0)],
+            Type::DamageOverTime => vec![all_in, Multiplier::new("DoT In", self.dot_in / 100.0)],
+            Type::TrueDamage => vec![],
+            Type::Heal => vec![Multiplier::new("Heal In", self.heal_in / 100.0)],
+        }
+    }
+
+    pub fn incoming_modifier(&self, damage_type: &Type) -> f32 {
+        product(&self.incoming_multipliers(damage_type))
+    }
+
+    /// What a skill costing `base` MP actually takes. Mana Consumption is a percentage, 100 is full cost.
+    pub fn mana_cost(&self, base: u32) -> i32 {
+        (base as f32 * self.mana_consumption / 100.0).round().max(0.0) as i32
+    }
+
 }
 
 
+/// Secondary stats split by when they are read during a fight.
+///
+/// The static layer is level, gear and permanent passives. DoTs snapshot it when applied.
+/// The dynamic layer is the static layer with timed buffs (passives with a `duration`) on top,
+/// and is what direct hits read at the moment they land.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct StatLayers {
+    pub static_stats: SecondaryStats,
+    pub dynamic_stats: SecondaryStats,
+}
+
+impl StatLayers {
+    pub fn new(static_stats: SecondaryStats, dynamic_stats: SecondaryStats) -> Self {
+        StatLayers {
+            static_stats,
+            dynamic_stats,
+        }
+    }
+}
+
+impl From<SecondaryStats> for StatLayers {
+    // No timed buffs active, both layers are the same
+    fn from(stats: SecondaryStats) -> Self {
+        StatLayers {
+            static_stats: stats.clone(),
+            dynamic_stats: stats,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PlayerStats {
     pub primary: PrimaryStats,
@@ -820,4 +869,15 @@ mod tests {
         println!("Primary Stats at level 100 for Hybrid Melee: {:?}", primary_stats);
 
     }
+
+    #[test]
+    fn test_mana_cost_and_use() {
+        let mut stats = SecondaryStats { mana_consumption: 100.0, mp: 100, current_mp: 20, ..Default::default() };
+        assert_eq!(stats.mana_cost(15), 15);
+        stats.mana_consumption = 80.0;
+        assert_eq!(stats.mana_cost(15), 12);
+
+        stats.use_mana(12);
+        assert_eq!(stats.current_mp, 8);
+    }
 }
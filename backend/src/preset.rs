@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use crate::{
+    build::Build,
+    enemy::{EnemyPhase, EnemySecondaryStats, EnemySkill, deserialize_enemy_stats},
+    error::BackendError,
+    passive::CustomPassive,
+};
+
+/// The bundled enemy catalog.
+const ENEMIES_JSON: &str = include_str!("../data/enemies.json");
+
+/// A named enemy setup: its stats and skill kit, ready to drop into a build.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct EnemyPreset {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Only the stats that differ from `EnemySecondaryStats::enemy_default` need to be listed.
+    #[serde(default = "EnemySecondaryStats::enemy_default", deserialize_with = "deserialize_enemy_stats")]
+    pub stats: EnemySecondaryStats,
+    #[serde(default)]
+    pub skills: Vec<(EnemySkill, Vec<CustomPassive>, bool)>,
+    #[serde(default)]
+    pub phases: Vec<EnemyPhase>,
+}
+
+impl EnemyPreset {
+    /// Case insensitive match on the name or any tag. An empty query matches everything.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        query.is_empty()
+            || self.name.to_lowercase().contains(&query)
+            || self.tags.iter().any(|t| t.to_lowercase().contains(&query))
+    }
+
+    /// Replaces the build's enemy, enemy skills and phases with this preset.
+    pub fn apply(&self, build: &mut Build) {
+        build.enemy = self.stats.clone();
+        build.enemy_skills = self.skills.clone();
+        build.enemy_phases = self.phases.clone();
+    }
+}
+
+/// Parses an enemy catalog, a list of presets.
+pub fn load_enemy_presets(json: &str) -> Result<Vec<EnemyPreset>, BackendError> {
+    serde_json::from_str(json).map_err(|e| BackendError::InvalidEnemyPreset(format!("Error: {e} ")))
+}
+
+/// The presets that ship with the app.
+pub fn enemy_presets() -> Vec<EnemyPreset> {
+    load_enemy_presets(ENEMIES_JSON).expect("bundled enemy catalog is valid")
+}
+
+/// Bundled presets whose name or tags match `query`.
+pub fn search_enemy_presets(query: &str) -> Vec<EnemyPreset> {
+    enemy_presets().into_iter().filter(|p| p.matches(query)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::damage::Target;
+
+    #[test]
+    fn test_bundled_catalog_loads() {
+        let presets = enemy_presets();
+        assert!(presets.len() >= 3);
+        let dummy = presets.iter().find(|p| p.name == "Target Dummy").unwrap();
+        assert!(dummy.skills.is_empty());
+        assert_eq!(dummy.stats.dodge, 0.0);
+        // Stats left out of the file keep their defaults
+        assert_eq!(dummy.stats.all_in, 100.0);
+        assert!(presets.iter().all(|p| p.skills.iter().all(|(s, _, _)| s.cooldown > 0)));
+        assert!(presets.iter().any(|p| !p.phases.is_empty()));
+    }
+
+    #[test]
+    fn test_search_and_apply() {
+        assert_eq!(search_enemy_presets("").len(), enemy_presets().len());
+        let ultras = search_enemy_presets("ULTRA");
+        assert!(!ultras.is_empty() && ultras.iter().all(|p| p.tags.contains(&"ultra".to_string())));
+        let engineer = &search_enemy_presets("engineer")[0];
+
+        let mut build = Build::default();
+        engineer.apply(&mut build);
+        assert_eq!(build.enemy, engineer.stats);
+        assert!(build.enemy_skills.iter().any(|(s, _, _)| s.target == Target::Yourself));
+    }
+
+    #[test]
+    fn test_unlisted_stats_use_enemy_defaults() {
+        let presets = load_enemy_presets(r#"[{"name": "Brute", "stats": {"crit_chance": 30.0}}, {"name": "Blank"}]"#).unwrap();
+        let defaults = EnemySecondaryStats::enemy_default();
+        assert_eq!(presets[0].stats, EnemySecondaryStats { crit_chance: 30.0, ..defaults.clone() });
+        assert_eq!(presets[1].stats, defaults);
+        assert_eq!(Build::default().enemy, defaults);
+        assert_eq!((defaults.haste, defaults.dodge), (37.5, 10.0));
+    }
+
+    #[test]
+    fn test_bad_catalog() {
+        assert!(load_enemy_presets(r#"[{"description": "no name"}]"#).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::error::BackendError;
+
+// --- Priority list ---
+//
+// A priority list is tried top to bottom every time the player could act, and the first
+// action whose skill is ready, affordable and whose conditions all hold gets cast.
+//
+// Text notation, one action per line (or separated by `;`), skills numbered from 1 like the UI:
+//
+//   3 if !buff(3)
+//   5 if enemy_hp < 30
+//   4 if stacks(4) >= 3 & mana > 40
+//   2
+//
+// Conditions are joined with `&` and negated with `!`. `buff(n)` is any timed effect skill n
+// put on the player or the enemy, `ready(n)` is skill n being off cooldown.
+// Metrics: `enemy_hp` and `hp` (percent), `mana` (MP), `stacks(n)`.
+
+/// A number the rotation can look at.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Metric {
+    /// Percent, always 100 against an enemy with infinite HP
+    EnemyHp,
+    /// The player's HP in percent
+    PlayerHp,
+    /// The player's MP
+    Mana,
+    Stacks(usize),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    Equal,
+}
+
+impl Comparison {
+    pub fn holds(&self, left: f32, right: f32) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessEq => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterEq => left >= right,
+            Comparison::Equal => left == right,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessEq => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterEq => ">=",
+            Comparison::Equal => "==",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Condition {
+    /// A timed effect from this skill is active on the player or the enemy
+    Buff(usize),
+    /// This skill is off cooldown (and off the GCD)
+    Ready(usize),
+    Compare(Metric, Comparison, f32),
+    Not(Box<Condition>),
+}
+
+/// What conditions are checked against, a snapshot of the fight at one moment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FightState {
+    pub enemy_hp: f32,
+    pub player_hp: f32,
+    pub mana: f32,
+    pub stacks: [u32; 5],
+    pub buffs: [bool; 5],
+    pub ready: [bool; 5],
+}
+
+impl Metric {
+    pub fn value(&self, state: &FightState) -> f32 {
+        match self {
+            Metric::EnemyHp => state.enemy_hp,
+            Metric::PlayerHp => state.player_hp,
+            Metric::Mana => state.mana,
+            Metric::Stacks(idx) => state.stacks.get(*idx).copied().unwrap_or(0) as f32,
+        }
+    }
+}
+
+impl Condition {
+    pub fn holds(&self, state: &FightState) -> bool {
+        match self {
+            Condition::Buff(idx) => state.buffs.get(*idx).copied().unwrap_or(false),
+            Condition::Ready(idx) => state.ready.get(*idx).copied().unwrap_or(false),
+            Condition::Compare(metric, cmp, value) => cmp.holds(metric.value(state), *value),
+            Condition::Not(inner) => !inner.holds(state),
+        }
+    }
+}
+
+/// One line of a priority list: a skill and what has to be true to cast it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PriorityAction {
+    pub skill: usize,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+}
+
+impl PriorityAction {
+    pub fn new(skill: usize) -> Self {
+        PriorityAction { skill, conditions: vec![] }
+    }
+
+    /// Whether the conditions allow a cast, readiness and MP are up to the simulator.
+    pub fn allows(&self, state: &FightState) -> bool {
+        self.conditions.iter().all(|c| c.holds(state))
+    }
+}
+
+// --- Notation ---
+
+fn invalid(text: &str, why: &str) -> BackendError {
+    BackendError::InvalidRotation(format!("Error: {why} in `{}` ", text.trim()))
+}
+
+/// A 1-based skill number from the notation to a skill index.
+fn parse_skill(text: &str, context: &str) -> Result<usize, BackendError> {
+    match text.trim().parse::<usize>() {
+        Ok(n @ 1..=5) => Ok(n - 1),
+        _ => Err(invalid(context, "skills go from 1 to 5")),
+    }
+}
+
+/// The skill number inside `name(n)`, if `text` is that call.
+fn call_arg<'t>(text: &'t str, name: &str) -> Option<&'t str> {
+    text.strip_prefix(name)?.trim_start().strip_prefix('(')?.strip_suffix(')')
+}
+
+impl Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Metric::EnemyHp => write!(f, "enemy_hp"),
+            Metric::PlayerHp => write!(f, "hp"),
+            Metric::Mana => write!(f, "mana"),
+            Metric::Stacks(idx) => write!(f, "stacks({})", idx + 1),
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "enemy_hp" => Ok(Metric::EnemyHp),
+            "hp" => Ok(Metric::PlayerHp),
+            "mana" | "mp" => Ok(Metric::Mana),
+            _ => match call_arg(s, "stacks") {
+                Some(arg) => Ok(Metric::Stacks(parse_skill(arg, s)?)),
+                None => Err(invalid(s, "unknown metric")),
+            },
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::Buff(idx) => write!(f, "buff({})", idx + 1),
+            Condition::Ready(idx) => write!(f, "ready({})", idx + 1),
+            Condition::Compare(metric, cmp, value) => write!(f, "{metric} {} {value}", cmp.as_str()),
+            Condition::Not(inner) => write!(f, "!{inner}"),
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(rest) = s.strip_prefix('!') {
+            return Ok(Condition::Not(Box::new(rest.parse()?)));
+        }
+        if let Some(arg) = call_arg(s, "buff") {
+            return Ok(Condition::Buff(parse_skill(arg, s)?));
+        }
+        if let Some(arg) = call_arg(s, "ready") {
+            return Ok(Condition::Ready(parse_skill(arg, s)?));
+        }
+        // Two character operators first so `<=` isn't read as `<`
+        let operators = [
+            (">=", Comparison::GreaterEq),
+            ("<=", Comparison::LessEq),
+            ("==", Comparison::Equal),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+            ("=", Comparison::Equal),
+        ];
+        for (op, cmp) in operators {
+            if let Some((left, right)) = s.split_once(op) {
+                let value = right.trim().parse::<f32>().map_err(|e| invalid(s, &e.to_string()))?;
+                return Ok(Condition::Compare(left.parse()?, cmp, value));
+            }
+        }
+        Err(invalid(s, "unknown condition"))
+    }
+}
+
+impl Display for PriorityAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.skill + 1)?;
+        for (i, condition) in self.conditions.iter().enumerate() {
+            write!(f, "{}{condition}", if i == 0 { " if " } else { " & " })?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PriorityAction {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (skill, conditions) = match s.split_once(" if ") {
+            Some((skill, conditions)) => (skill, Some(conditions)),
+            None => (s, None),
+        };
+        let conditions = match conditions {
+            Some(text) => text.split('&').map(Condition::from_str).collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+        Ok(PriorityAction { skill: parse_skill(skill, s)?, conditions })
+    }
+}
+
+/// Reads a priority list from its text notation. Blank lines and `#` comments are skipped.
+pub fn parse_priority_list(text: &str) -> Result<Vec<PriorityAction>, BackendError> {
+    text.split(['\n', ';'])
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(PriorityAction::from_str)
+        .collect()
+}
+
+/// The text notation of a priority list, one action per line.
+pub fn priority_list_to_string(list: &[PriorityAction]) -> String {
+    list.iter().map(|a| a.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_print() {
+        let text = "3 if !buff(3)\n5 if enemy_hp < 30\n4 if stacks(4) >= 3 & mana > 40\n2";
+        let list = parse_priority_list(text).unwrap();
+        assert_eq!(list[0], PriorityAction { skill: 2, conditions: vec![Condition::Not(Box::new(Condition::Buff(2)))] });
+        assert_eq!(list[2].conditions, vec![
+            Condition::Compare(Metric::Stacks(3), Comparison::GreaterEq, 3.0),
+            Condition::Compare(Metric::Mana, Comparison::Greater, 40.0),
+        ]);
+        assert_eq!(list[3], PriorityAction::new(1));
+        assert_eq!(priority_list_to_string(&list), text);
+    }
+
+    #[test]
+    fn test_one_line_and_comments() {
+        let list = parse_priority_list("2 if ready(3) ; 3  # filler\n\n# nothing here").unwrap();
+        assert_eq!(list, vec![PriorityAction { skill: 1, conditions: vec![Condition::Ready(2)] }, PriorityAction::new(2)]);
+    }
+
+    #[test]
+    fn test_bad_notation() {
+        for bad in ["6", "x", "2 if buff(9)", "2 if mana >", "2 if speed > 3", "2 if"] {
+            assert!(parse_priority_list(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_conditions() {
+        let state = FightState { enemy_hp: 25.0, mana: 40.0, stacks: [0, 0, 0, 3, 0], buffs: [false, false, true, false, false], ..Default::default() };
+        let list = parse_priority_list("3 if !buff(3); 5 if enemy_hp < 30; 4 if stacks(4) >= 3 & mana > 40").unwrap();
+        assert!(!list[0].allows(&state));
+        assert!(list[1].allows(&state));
+        assert!(!list[2].allows(&state));
+    }
+}
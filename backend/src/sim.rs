@@ -0,0 +1,920 @@
+use serde::{Deserialize, Serialize};
+use crate::{
+    damage::{ActiveDot, HealResult, Skill, StackGain, StackTracker, Target, Type, Weapon},
+    enemy::{EnemyPhase, EnemySecondaryStats, EnemySkill, PhaseTrigger},
+    monte_carlo::{MonteCarloReport, Rng},
+    passive::{CustomPassive, TargetType, apply_passives},
+    rotation::{FightState, PriorityAction},
+    player::{SecondaryStats, StatLayers},
+    stat::{StatKey, capped},
+};
+
+/// Global cooldown in seconds before haste, shared by every skill except the auto attack
+pub const GCD: f32 = 1.5;
+
+/// What the simulator reads from a build.
+pub trait Loadout {
+    /// Static layer from permanent passives, dynamic layer with `buffs` on top
+    fn stat_layers(&self, buffs: &[CustomPassive]) -> StatLayers;
+    fn weapon(&self) -> &Weapon;
+    /// Skill, the passives it applies on cast, and whether it can crit
+    fn skills(&self) -> &[(Skill, Vec<CustomPassive>, bool)];
+    fn enemy(&self) -> &EnemySecondaryStats;
+    /// Enemy skill, the passives it applies on cast, and whether it can crit
+    fn enemy_skills(&self) -> &[(EnemySkill, Vec<CustomPassive>, bool)];
+    /// Phases after the opening one, in the order they trigger
+    fn enemy_phases(&self) -> &[EnemyPhase] { &[] }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RotationAction {
+    Skill(usize),
+    Delay(f32),
+}
+
+/// Everything about a fight that isn't part of the build itself.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FightSettings {
+    /// Seconds
+    pub duration: f32,
+    /// 0 or less fights a dummy that never dies
+    pub enemy_hp: i32,
+    /// Auto attack runs on its own timer next to the rotation
+    pub auto_attack: bool,
+    /// MP each landed auto attack gives back
+    pub aa_mana: i32,
+}
+
+impl Default for FightSettings {
+    fn default() -> Self {
+        FightSettings {
+            duration: 60.0,
+            enemy_hp: 0,
+            auto_attack: true,
+            aa_mana: 4,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct SimResult {
+    pub total_dmg: f32,
+    pub cast_counts: [u32; 5],
+    /// Direct and DoT damage per skill
+    pub skill_damage: [f32; 5],
+    /// Time and skill index of every cast, in order
+    pub casts: Vec<(f32, usize)>,
+    pub dps: f32,
+    /// All healing the player received, lifesteal included
+    pub healing: HealResult,
+    pub lifesteal: HealResult,
+    pub hps: f32,
+    /// Stack count of every skill each time one changes
+    pub stack_history: Vec<(f32, [u32; 5])>,
+    /// MP after every change
+    pub mana_history: Vec<(f32, f32)>,
+    /// Seconds the rotation spent waiting on MP
+    pub mana_starved: f32,
+    pub player_hp: i32,
+    pub player_died: bool,
+    /// Damage the player took from enemy skills and their own self-damage
+    pub damage_taken: f32,
+    pub dtps: f32,
+    pub enemy_hp: i32,
+    pub enemy_defeated: bool,
+    pub time: f32,
+    /// Name and seconds spent in every enemy phase the fight reached, in order
+    pub phase_times: Vec<(String, f32)>,
+}
+
+#[derive(Clone)]
+struct ActiveBuff {
+    skill_idx: usize,
+    passive_idx: usize,
+    remaining_ms: f32,
+    passive: CustomPassive
+}
+
+/// What a single cast does, before it's applied to anyone's HP.
+struct CastResult {
+    enemy_dmg: f32,
+    self_dmg: f32,
+    /// Healing on the player, already scaled by Heal In
+    heal: f32,
+    /// HP stolen back by `hp_back`, already scaled by Heal In
+    lifesteal: f32,
+    /// 0 or 1 when rolled, the land chance otherwise
+    landed: f32,
+    /// MP given back by `mana_back`
+    mana: f32,
+}
+
+impl CastResult {
+    /// The enemy is invulnerable: nothing reaches it, so nothing is stolen back either.
+    fn absorb(&mut self) {
+        self.enemy_dmg = 0.0;
+        self.lifesteal = 0.0;
+    }
+}
+
+/// Haste as a fraction, clamped to the hard cap
+pub fn haste_factor(stats: &SecondaryStats) -> f32 {
+    StatKey::Haste.clamp(stats.haste) / 100.0
+}
+
+/// Seconds until a skill with a `cd_ms` cooldown is ready again at `haste` (a fraction)
+pub fn cooldown(cd_ms: u32, haste: f32) -> f32 {
+    ((cd_ms as f32 / 1000.0) * (1.0 - haste)).max(0.001)
+}
+
+/// A fight between a build and its enemy, following a fixed rotation or a priority list.
+pub struct Simulation<'a> {
+    build: &'a dyn Loadout,
+    rotation: &'a [RotationAction],
+    priority: &'a [PriorityAction],
+    settings: FightSettings,
+}
+
+impl<'a> Simulation<'a> {
+    /// Casts `rotation` in order, over and over.
+    pub fn new(build: &'a dyn Loadout, rotation: &'a [RotationAction], settings: FightSettings) -> Self {
+        Simulation { build, rotation, priority: &[], settings }
+    }
+
+    /// Casts the first action of `priority` that qualifies every time the player can act.
+    pub fn with_priority(build: &'a dyn Loadout, priority: &'a [PriorityAction], settings: FightSettings) -> Self {
+        Simulation { build, rotation: &[], priority, settings }
+    }
+
+    /// Runs the fight `iterations` times with rolled crits, hits and dodges.
+    pub fn monte_carlo(&self, seed: u64, iterations: usize) -> MonteCarloReport {
+        MonteCarloReport::run(seed, iterations, |rng| {
+            let result = self.run(Some(rng));
+            (result.dps, result.enemy_defeated.then_some(result.time))
+        })
+    }
+
+    // =========================================================================
+    // SIMULATION ENGINE
+    // =========================================================================
+    // Runs one fight. Without an RNG every hit deals its expected damage, with one
+    // crits, misses and dodges are rolled so the fight can be sampled many times.
+    // If you need to track another resource, add it to the setup variables here.
+    pub fn run(&self, mut rng: Option<&mut Rng>) -> SimResult {
+        let build = self.build;
+        let config = &self.settings;
+        let priority = self.priority;
+        let weapon = build.weapon();
+        let skills = build.skills();
+        // A saved rotation can point past the skill list, those steps are skipped like unusable priority actions
+        let rotation: Vec<RotationAction> = self.rotation.iter().copied()
+            .filter(|a| !matches!(a, RotationAction::Skill(idx) if *idx >= skills.len()))
+            .collect();
+        let phases = build.enemy_phases();
+        let mut enemy_skills = build.enemy_skills();
+
+        // --- 1. Setup ---
+        let duration = config.duration;
+        let mut time: f32 = 0.0;
+        let mut total_dmg: f32 = 0.0;
+        let mut cast_counts = [0_u32; 5];
+        let mut skill_damage = [0.0_f32; 5];
+        let mut casts = Vec::new();
+        let mut enemy_dots: Vec<ActiveDot> = Vec::new();
+        let mut player_hots: Vec<ActiveDot> = Vec::new();
+        let mut stacks: [Option<StackTracker>; 5] = std::array::from_fn(|i| {
+            skills.get(i).and_then(|(s, _, _)| s.properties.stacking).map(StackTracker::new)
+        });
+        let mut stack_history = vec![(0.0, stack_counts(&stacks))];
+        let mut healing = HealResult::default();
+        // Part of `healing` that came from lifesteal
+        let mut lifesteal = HealResult::default();
+
+        let mut active_buffs: Vec<ActiveBuff> = Vec::new();
+        let mut active_enemy_debuffs: Vec<ActiveBuff> = Vec::new();
+
+        // Cooldown trackers (Absolute time in seconds when they are ready)
+        let mut gcd_ready_at: f32 = 0.0;
+        let mut cd_ready_at: [f32; 5] = [0.0; 5];
+        let mut enemy_cd_ready_at = vec![0.0; enemy_skills.len()];
+
+        let mut rot_wait_until: f32 = 0.0;
+        let mut rotation_idx = 0;
+
+        // --- 2. Helper Functions ---
+        // Splits the player's stats into the static layer and the dynamic layer with all current active buffs applied
+        let get_effective_stats = |actives: &Vec<ActiveBuff>| -> StatLayers {
+            let buffs: Vec<CustomPassive> = actives.iter().map(|b| b.passive.clone()).collect();
+            build.stat_layers(&buffs)
+        };
+
+        let initial_stats = get_effective_stats(&active_buffs);
+        let mut running_player_hp = initial_stats.dynamic_stats.hp;
+        let max_hp = initial_stats.dynamic_stats.hp;
+        // Kept as a float so expected-value mana gains don't get rounded away
+        let max_mp = initial_stats.dynamic_stats.mp as f32;
+        let mut running_mp = initial_stats.dynamic_stats.current_mp as f32;
+        // Stats skill costs were last worked out with, Phase A runs before this event's stats exist
+        let mut cost_stats = initial_stats.dynamic_stats.clone();
+        let mut mana_history = vec![(0.0, running_mp)];
+        let mut mana_starved: f32 = 0.0;
+        let mut player_died = false;
+        let mut damage_taken: f32 = 0.0;
+
+        let has_finite_hp = config.enemy_hp > 0;
+        let mut running_enemy_hp = config.enemy_hp;
+        let mut enemy_defeated = false;
+
+        // The opening phase is the build's own enemy, `phase` counts the phases entered since
+        let mut phase = 0;
+        let mut phase_started: f32 = 0.0;
+        let mut phase_enemy = build.enemy().clone();
+        let mut phase_times = Vec::new();
+        let mut invulnerable_until: f32 = 0.0;
+        let phase_name = |phase: usize| if phase == 0 { "Opening".to_string() } else { phases[phase - 1].name.clone() };
+
+        // Calculates the enemy's stats with all current active debuffs applied
+        let get_effective_enemy = |base: &EnemySecondaryStats, debuffs: &Vec<ActiveBuff>| -> EnemySecondaryStats {
+            capped(&apply_passives(base, TargetType::Enemy, debuffs.iter().map(|d| &d.passive)))
+        };
+
+        // MP skill `s_idx` costs with the given Mana Consumption
+        let mana_cost = |s_idx: usize, stats: &SecondaryStats| -> f32 {
+            skills[s_idx].0.mana_cost(stats) as f32
+        };
+
+        // The skill as it is right now, with its live stack count instead of the configured one
+        let skill_with_stacks = |s_idx: usize, stacks: &[Option<StackTracker>; 5]| -> Skill {
+            let mut skill = skills[s_idx].0;
+            if let Some(tracker) = &stacks[s_idx] {
+                skill.properties.stacking = Some(tracker.function);
+            }
+            skill
+        };
+
+        // Calculates what a specific skill does. Rolls crit/hit when given an RNG, otherwise uses the
+        // expected value (Crit + Non-Crit weighted, scaled by the chance to land).
+        // `skill` carries the live stack count, so it's passed in rather than read from the build.
+        let compute_cast = |skill: &Skill, layers: &StatLayers, enemy: &EnemySecondaryStats, rng: Option<&mut Rng>| -> CastResult {
+            let secondary = skill.damage_type.layer(layers);
+
+            // Heals always land on the caster, there's no party to heal
+            if skill.damage_type == Type::Heal {
+                let heal_in = layers.dynamic_stats.heal_in;
+                let heal = match rng {
+                    Some(rng) => skill.roll_heal(weapon, layers, heal_in, rng),
+                    None => skill.expected_heal(weapon, layers, heal_in),
+                };
+                let mana = skill.properties.mana_back.unwrap_or(0) as f32;
+                let lifesteal = skill.lifesteal(0.0, 1.0, weapon, &layers.dynamic_stats);
+                return CastResult { enemy_dmg: 0.0, self_dmg: 0.0, heal, lifesteal, landed: 1.0, mana };
+            }
+
+            let (avg_raw, landed) = match rng {
+                Some(rng) => match skill.roll_hit(weapon, layers, enemy.dodge, rng) {
+                    Some(dmg) => (dmg, 1.0),
+                    None => (0.0, 0.0),
+                },
+                None => (
+                    skill.expected(weapon, layers, enemy.dodge),
+                    skill.land_chance(secondary.hit_chance, enemy.dodge),
+                ),
+            };
+
+            // mana_back only procs when the skill lands
+            let mana = skill.properties.mana_back.unwrap_or(0) as f32 * landed;
+
+            if skill.target == Target::Yourself {
+                // A damaging skill aimed at yourself hurts you
+                CastResult { enemy_dmg: 0.0, self_dmg: avg_raw, heal: 0.0, lifesteal: 0.0, landed, mana }
+            } else {
+                let enemy_dmg = avg_raw * enemy.incoming_modifier(&skill.damage_type);
+                let lifesteal = skill.lifesteal(enemy_dmg, landed, weapon, &layers.dynamic_stats);
+                CastResult { enemy_dmg, self_dmg: 0.0, heal: 0.0, lifesteal, landed, mana }
+            }
+        };
+
+        // Snapshots and applies the skill's DoT to the enemy and HoT to the player. An expected-value hit
+        // applies a DoT scaled by its land chance, a rolled miss applies nothing. HoTs always apply.
+        let apply_over_time = |s_idx: usize, layers: &StatLayers, landed: f32, dots: &mut Vec<ActiveDot>, hots: &mut Vec<ActiveDot>, now: f32| {
+            let (skill, _, _) = &skills[s_idx];
+            if let Some(effect) = &skill.properties.dot
+                && skill.target == Target::Enemy
+                && landed > 0.0
+            {
+                let tick_damage = effect.snapshot(skill, weapon, layers) * landed;
+                ActiveDot::apply(dots, s_idx, effect, tick_damage, now);
+            }
+            if let Some(effect) = &skill.properties.hot {
+                let tick_heal = effect.heal_snapshot(skill, weapon, layers);
+                ActiveDot::apply(hots, s_idx, effect, tick_heal, now);
+            }
+        };
+
+        // Heals the player up to max HP, tracking effective healing and overheal
+        let heal_player = |amount: f32, stats: &mut SecondaryStats, healing: &mut HealResult| -> HealResult {
+            let heal = HealResult::apply(amount, stats.current_hp as f32, stats.hp as f32);
+            stats.current_hp += heal.effective as i32;
+            healing.add(&heal);
+            heal
+        };
+
+        // Helper to apply buffs/debuffs when a skill is cast
+        let apply_passives = |s_idx: usize, passives: &Vec<CustomPassive>, e_debuffs: &mut Vec<ActiveBuff>, p_buffs: &mut Vec<ActiveBuff>| {
+            for (p_idx, passive) in passives.iter().enumerate() {
+                let target_list = if passive.target_type == TargetType::Enemy { &mut *e_debuffs } else { &mut *p_buffs };
+
+                // Refresh duration if it already exists, otherwise add new
+                if let Some(existing) = target_list.iter_mut().find(|b| b.skill_idx == s_idx && b.passive_idx == p_idx) {
+                    if let Some(d) = passive.duration { existing.remaining_ms = d as f32; }
+                } else {
+                    target_list.push(ActiveBuff {
+                        skill_idx: s_idx, passive_idx: p_idx,
+                        remaining_ms: passive.duration.unwrap_or(0) as f32,
+                        passive: passive.clone(),
+                    });
+                }
+            }
+        };
+
+        // --- 3. Main Event Loop ---
+        let mut safety_net = 0;
+        while time < duration && safety_net < 100_000 {
+            safety_net += 1;
+
+            // Phase A: Predict the next event time
+            let next_aa_time = if config.auto_attack { cd_ready_at[0].max(time) } else { f32::INFINITY };
+            let next_enemy_time = enemy_cd_ready_at.iter().copied().fold(f32::INFINITY, f32::min);
+            let next_dot_time = enemy_dots.iter().map(|d| d.next_tick).fold(f32::INFINITY, f32::min);
+            let next_hot_time = player_hots.iter().map(|d| d.next_tick).fold(f32::INFINITY, f32::min);
+            let next_decay_time = stacks.iter().flatten().filter_map(|t| t.expires_at).fold(f32::INFINITY, f32::min);
+            // HP triggers are checked after every event, only timed ones need an event of their own
+            let next_phase_time = match phases.get(phase).map(|p| p.trigger) {
+                Some(PhaseTrigger::Time(at)) => at.max(time),
+                _ => f32::INFINITY,
+            };
+            let mut next_rot_time = f32::INFINITY;
+            // Set when the next rotation skill is ready but unaffordable, it waits for mana to come back
+            let mut mana_blocked = false;
+
+            if !rotation.is_empty() {
+                if rot_wait_until > time {
+                    next_rot_time = rot_wait_until;
+                } else {
+                    match &rotation[rotation_idx] {
+                        RotationAction::Skill(idx) => {
+                            if *idx == 0 {
+                                next_rot_time = if config.auto_attack { time } else { cd_ready_at[0].max(time) };
+                            } else {
+                                // Skill must wait for both its own CD and the Global CD
+                                next_rot_time = cd_ready_at[*idx].max(gcd_ready_at).max(time);
+                            }
+                            // Ready but can't be paid for, only a mana gain can unblock it
+                            if next_rot_time <= time && !(*idx == 0 && config.auto_attack) && mana_cost(*idx, &cost_stats) > running_mp {
+                                next_rot_time = f32::INFINITY;
+                                mana_blocked = true;
+                            }
+                        }
+                        RotationAction::Delay(_) => next_rot_time = time,
+                    }
+                }
+            }
+
+            // Priority list: the first action that qualifies now, otherwise wait for the next skill to come off cooldown.
+            // Buffs running out can flip a condition, so their expiry is an event too.
+            let mut priority_pick = None;
+            let mut next_expiry_time = f32::INFINITY;
+            if !priority.is_empty() {
+                let ready_at = |idx: usize| if idx == 0 { cd_ready_at[0] } else { cd_ready_at[idx].max(gcd_ready_at) };
+                let has_effect = |idx: usize| {
+                    active_buffs.iter().chain(&active_enemy_debuffs).any(|b| b.skill_idx == idx && b.passive.duration.is_some())
+                        || enemy_dots.iter().chain(&player_hots).any(|d| d.source == idx)
+                };
+                let state = FightState {
+                    enemy_hp: if has_finite_hp { running_enemy_hp as f32 / config.enemy_hp as f32 * 100.0 } else { 100.0 },
+                    player_hp: if max_hp > 0 { running_player_hp as f32 / max_hp as f32 * 100.0 } else { 100.0 },
+                    mana: running_mp,
+                    stacks: stack_counts(&stacks),
+                    buffs: std::array::from_fn(has_effect),
+                    ready: std::array::from_fn(|i| ready_at(i) <= time),
+                };
+                // The auto attack already runs on its own timer
+                let usable = |a: &&PriorityAction| a.skill < skills.len() && !(a.skill == 0 && config.auto_attack);
+
+                for action in priority.iter().filter(usable) {
+                    if ready_at(action.skill) <= time && action.allows(&state) {
+                        if mana_cost(action.skill, &cost_stats) <= running_mp {
+                            priority_pick = Some(action.skill);
+                            break;
+                        }
+                        mana_blocked = true;
+                    }
+                }
+                if priority_pick.is_some() {
+                    mana_blocked = false;
+                    next_rot_time = time;
+                } else {
+                    next_rot_time = priority.iter().filter(usable).map(|a| ready_at(a.skill)).filter(|&t| t > time).fold(f32::INFINITY, f32::min);
+                }
+                next_expiry_time = active_buffs.iter().chain(&active_enemy_debuffs)
+                    .filter(|b| b.passive.duration.is_some())
+                    .map(|b| time + b.remaining_ms / 1000.0)
+                    .fold(f32::INFINITY, f32::min);
+            }
+
+            // If no events left, end simulation
+            let t_event = next_aa_time.min(next_rot_time).min(next_enemy_time).min(next_dot_time).min(next_hot_time).min(next_decay_time).min(next_phase_time).min(next_expiry_time);
+            if t_event >= duration || t_event == f32::INFINITY {
+                if mana_blocked { mana_starved += duration - time; }
+                time = duration;
+                break;
+            }
+
+            // Phase B: Advance time and Decay Auras
+            let delta_ms = (t_event - time) * 1000.0;
+            if mana_blocked { mana_starved += t_event - time; }
+            time = t_event;
+
+            active_buffs.retain_mut(|b| { b.remaining_ms -= delta_ms; b.passive.duration.is_none() || b.remaining_ms > 0.0 });
+            active_enemy_debuffs.retain_mut(|b| { b.remaining_ms -= delta_ms; b.passive.duration.is_none() || b.remaining_ms > 0.0 });
+
+            // Recalculate stats for this exact moment in time
+            let mut current_layers = get_effective_stats(&active_buffs);
+            let current_enemy = get_effective_enemy(&phase_enemy, &active_enemy_debuffs);
+            // Hits and ticks still happen while the enemy is invulnerable, they just deal nothing
+            let invulnerable = time < invulnerable_until;
+            let curr_h = haste_factor(&current_layers.dynamic_stats);
+            cost_stats = current_layers.dynamic_stats.clone();
+            current_layers.dynamic_stats.current_mp = running_mp as i32;
+
+            current_layers.dynamic_stats.current_hp = running_player_hp;
+
+            let mut action_taken = false;
+
+            // Phase C: Enemy Actions (They hit first if tied)
+            for (e_idx, (e_skill, e_passives, e_crit)) in enemy_skills.iter().enumerate() {
+                if time == enemy_cd_ready_at[e_idx] {
+                    let amount = match rng.as_deref_mut() {
+                        Some(rng) => e_skill.roll(&current_enemy, &current_layers.dynamic_stats, *e_crit, rng),
+                        None => e_skill.expected(&current_enemy, &current_layers.dynamic_stats, *e_crit),
+                    };
+
+                    if e_skill.hits_player() {
+                        current_layers.dynamic_stats.current_hp -= amount as i32;
+                        damage_taken += amount;
+                    } else if e_skill.damage_type == Type::Heal && has_finite_hp {
+                        running_enemy_hp = (running_enemy_hp + amount as i32).min(config.enemy_hp);
+                    }
+                    apply_passives(100 + e_idx, e_passives, &mut active_enemy_debuffs, &mut active_buffs);
+
+                    enemy_cd_ready_at[e_idx] = time + (e_skill.cooldown as f32 / 1000.0).max(0.001);
+                    action_taken = true;
+                }
+            }
+
+            if current_layers.dynamic_stats.current_hp <= 0 {
+                player_died = true;
+                running_player_hp = 0;
+                break;
+            }
+
+            // Phase C2: DoT ticks, credited to the skill that applied them.
+            // The snapshot is fixed but the enemy's DoT In is read live.
+            if time == next_dot_time {
+                let dot_mod = current_enemy.incoming_modifier(&Type::DamageOverTime);
+                for dot in enemy_dots.iter_mut().filter(|d| d.next_tick <= time) {
+                    let tick = if invulnerable { 0.0 } else { dot.damage() * dot_mod };
+                    total_dmg += tick;
+                    skill_damage[dot.source] += tick;
+                    running_enemy_hp -= tick as i32;
+                    dot.advance();
+                }
+                enemy_dots.retain(|d| d.is_running());
+                action_taken = true;
+
+                if has_finite_hp && running_enemy_hp <= 0 {
+                    enemy_defeated = true;
+                    running_enemy_hp = 0;
+                    break;
+                }
+            }
+
+            // Stacks that ran out of time drop before anything else happens
+            if time == next_decay_time {
+                stacks.iter_mut().flatten().for_each(|t| t.update(time));
+                stack_history.push((time, stack_counts(&stacks)));
+                action_taken = true;
+            }
+
+            // HoT ticks read the player's live Heal In
+            if time == next_hot_time {
+                let heal_in = current_layers.dynamic_stats.heal_in / 100.0;
+                for hot in player_hots.iter_mut().filter(|d| d.next_tick <= time) {
+                    heal_player(hot.damage() * heal_in, &mut current_layers.dynamic_stats, &mut healing);
+                    hot.advance();
+                }
+                player_hots.retain(|d| d.is_running());
+                action_taken = true;
+            }
+
+            // Phase D: Execute Rotation Action
+            //
+            // An auto attack that can't be paid for is skipped until its next swing
+            if config.auto_attack && time == next_aa_time && mana_cost(0, &cost_stats) > running_mp {
+                cd_ready_at[0] = time + cooldown(skills[0].0.cd, curr_h);
+                action_taken = true;
+            } else if config.auto_attack && time == next_aa_time {
+                let skill = skill_with_stacks(0, &stacks);
+                let mut cast = compute_cast(&skill, &current_layers, &current_enemy, rng.as_deref_mut());
+                if invulnerable { cast.absorb(); }
+                update_stacks(&mut stacks, 0, &skill, &cast, time);
+                stack_history.push((time, stack_counts(&stacks)));
+                let e_dmg = cast.enemy_dmg;
+                total_dmg += e_dmg;
+                skill_damage[0] += e_dmg;
+                casts.push((time, 0));
+                apply_over_time(0, &current_layers, cast.landed, &mut enemy_dots, &mut player_hots, time);
+
+                // Apply damage to enemy and check death condition
+                running_enemy_hp -= e_dmg as i32;
+                if has_finite_hp && running_enemy_hp <= 0 {
+                    enemy_defeated = true;
+                    running_enemy_hp = 0;
+                    break;
+                }
+
+                current_layers.dynamic_stats.current_hp -= cast.self_dmg as i32;
+                damage_taken += cast.self_dmg;
+                heal_player(cast.heal, &mut current_layers.dynamic_stats, &mut healing);
+                lifesteal.add(&heal_player(cast.lifesteal, &mut current_layers.dynamic_stats, &mut healing));
+
+                // Landed auto attacks give MP back
+                let mana = cast.mana + config.aa_mana as f32 * cast.landed - mana_cost(0, &cost_stats);
+                running_mp = (running_mp + mana).clamp(0.0, max_mp);
+                mana_history.push((time, running_mp));
+
+                cast_counts[0] += 1;
+                apply_passives(0, &skills[0].1, &mut active_enemy_debuffs, &mut active_buffs);
+                cd_ready_at[0] = time + cooldown(skills[0].0.cd, curr_h);
+                action_taken = true;
+            }
+            // Which skill the rotation casts now, if any
+            let mut rot_cast = None;
+            if !rotation.is_empty() && time == next_rot_time {
+                if rot_wait_until <= time {
+                    if let RotationAction::Skill(s_idx) = rotation[rotation_idx] {
+                        let ready = !(s_idx == 0 && config.auto_attack) && time >= cd_ready_at[s_idx] && (s_idx == 0 || time >= gcd_ready_at);
+                        // Without enough MP the rotation holds this spot until there is
+                        let affordable = mana_cost(s_idx, &cost_stats) <= running_mp;
+                        if ready && affordable {
+                            rot_cast = Some(s_idx);
+                        }
+                        if !ready || affordable {
+                            rotation_idx = (rotation_idx + 1) % rotation.len();
+                        }
+                    } else if let RotationAction::Delay(d) = rotation[rotation_idx] {
+                        rot_wait_until = time + d;
+                        rotation_idx = (rotation_idx + 1) % rotation.len();
+                    }
+                }
+                action_taken = true;
+            }
+            if !priority.is_empty() && (time == next_rot_time || time == next_expiry_time) {
+                // The auto attack may have spent the MP since the pick was made
+                rot_cast = priority_pick.filter(|&s_idx| mana_cost(s_idx, &cost_stats) <= running_mp);
+                action_taken = true;
+            }
+            if let Some(s_idx) = rot_cast {
+                let skill = skill_with_stacks(s_idx, &stacks);
+                let mut cast = compute_cast(&skill, &current_layers, &current_enemy, rng.as_deref_mut());
+                if invulnerable { cast.absorb(); }
+                update_stacks(&mut stacks, s_idx, &skill, &cast, time);
+                stack_history.push((time, stack_counts(&stacks)));
+                let e_dmg = cast.enemy_dmg;
+                total_dmg += e_dmg;
+                skill_damage[s_idx] += e_dmg;
+                casts.push((time, s_idx));
+                apply_over_time(s_idx, &current_layers, cast.landed, &mut enemy_dots, &mut player_hots, time);
+
+                running_enemy_hp -= e_dmg as i32;
+                if has_finite_hp && running_enemy_hp <= 0 {
+                    enemy_defeated = true;
+                    running_enemy_hp = 0;
+                    break;
+                }
+
+                current_layers.dynamic_stats.current_hp -= cast.self_dmg as i32;
+                damage_taken += cast.self_dmg;
+                heal_player(cast.heal, &mut current_layers.dynamic_stats, &mut healing);
+                lifesteal.add(&heal_player(cast.lifesteal, &mut current_layers.dynamic_stats, &mut healing));
+
+                running_mp = (running_mp - mana_cost(s_idx, &cost_stats) + cast.mana).clamp(0.0, max_mp);
+                mana_history.push((time, running_mp));
+
+                cast_counts[s_idx] += 1;
+                apply_passives(s_idx, &skills[s_idx].1, &mut active_enemy_debuffs, &mut active_buffs);
+
+                cd_ready_at[s_idx] = time + cooldown(skills[s_idx].0.cd, curr_h);
+                if s_idx != 0 { gcd_ready_at = time + GCD * (1.0 - curr_h); }
+            }
+
+            running_player_hp = current_layers.dynamic_stats.current_hp;
+
+            if running_player_hp <= 0 {
+                player_died = true;
+                break;
+            }
+
+            // Phase E: Enemy phase changes. Several triggers met at once skip straight to the last one.
+            let hp_percent = has_finite_hp.then(|| running_enemy_hp as f32 / config.enemy_hp as f32 * 100.0);
+            while let Some(next) = phases.get(phase)
+                && next.trigger.reached(time, hp_percent)
+            {
+                phase_times.push((phase_name(phase), time - phase_started));
+                phase += 1;
+                phase_started = time;
+                phase_enemy = next.stats(build.enemy());
+                enemy_skills = &next.skills;
+                // The new kit starts right away
+                enemy_cd_ready_at = vec![time; enemy_skills.len()];
+                invulnerable_until = time + next.invulnerable;
+                action_taken = true;
+            }
+
+            if !action_taken { time += 0.001; }
+        }
+
+        mana_history.push((time.min(duration), running_mp));
+        phase_times.push((phase_name(phase), time.min(duration) - phase_started));
+
+        // A player who starts with no HP dies at 0s
+        let elapsed = time.min(duration);
+        let final_dps = if elapsed > 0.0 { total_dmg / elapsed } else { 0.0 };
+        let hps = if elapsed > 0.0 { healing.effective / elapsed } else { 0.0 };
+        let dtps = if elapsed > 0.0 { damage_taken / elapsed } else { 0.0 };
+        SimResult {
+            total_dmg,
+            cast_counts,
+            skill_damage,
+            casts,
+            dps: final_dps,
+            healing,
+            lifesteal,
+            hps,
+            stack_history,
+            mana_history,
+            mana_starved,
+            player_hp: running_player_hp,
+            player_died,
+            damage_taken,
+            dtps,
+            enemy_hp: running_enemy_hp,
+            enemy_defeated,
+            time,
+            phase_times,
+        }
+    }
+}
+
+fn stack_counts(stacks: &[Option<StackTracker>; 5]) -> [u32; 5] {
+    std::array::from_fn(|i| stacks[i].map_or(0, |t| t.stacks()))
+}
+
+// After skill `s_idx` is cast: it spends its own stacks first, then every stacking skill
+// gains from the cast (and from the hit, if it landed on the enemy).
+// Expected-value casts count any hit that can land, misses only show up in Monte Carlo.
+fn update_stacks(stacks: &mut [Option<StackTracker>; 5], s_idx: usize, skill: &Skill, cast: &CastResult, now: f32) {
+    if let Some(tracker) = &mut stacks[s_idx] {
+        tracker.on_use();
+    }
+    let hit = skill.target == Target::Enemy && skill.damage_type != Type::Heal && cast.landed > 0.0;
+    for tracker in stacks.iter_mut().flatten() {
+        match tracker.function.gain() {
+            StackGain::PerCast => tracker.gain(now),
+            StackGain::PerHit if hit => tracker.gain(now),
+            StackGain::PerHit => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{damage::ForceResult, rotation::parse_priority_list};
+
+    struct TestBuild {
+        stats: SecondaryStats,
+        weapon: Weapon,
+        skills: Vec<(Skill, Vec<CustomPassive>, bool)>,
+        enemy: EnemySecondaryStats,
+        enemy_skills: Vec<(EnemySkill, Vec<CustomPassive>, bool)>,
+        enemy_phases: Vec<EnemyPhase>,
+    }
+
+    impl TestBuild {
+        // Five free skills with the given cooldowns and a player that can't die
+        fn new(cds: [u32; 5], haste: f32) -> Self {
+            let stats = SecondaryStats { haste, hp: 1000, current_hp: 1000, ..Default::default() };
+            let skills = cds.iter().map(|&cd| (Skill { cd, mp: 0, ..Default::default() }, vec![], false)).collect();
+            TestBuild {
+                stats,
+                weapon: Weapon { range: 1.0, dps: 100.0, boost: Default::default() },
+                skills,
+                enemy: EnemySecondaryStats::default(),
+                enemy_skills: vec![],
+                enemy_phases: vec![],
+            }
+        }
+    }
+
+    impl Loadout for TestBuild {
+        fn stat_layers(&self, _buffs: &[CustomPassive]) -> StatLayers { StatLayers::from(self.stats.clone()) }
+        fn weapon(&self) -> &Weapon { &self.weapon }
+        fn skills(&self) -> &[(Skill, Vec<CustomPassive>, bool)] { &self.skills }
+        fn enemy(&self) -> &EnemySecondaryStats { &self.enemy }
+        fn enemy_skills(&self) -> &[(EnemySkill, Vec<CustomPassive>, bool)] { &self.enemy_skills }
+        fn enemy_phases(&self) -> &[EnemyPhase] { &self.enemy_phases }
+    }
+
+    fn cast_times(result: &SimResult, s_idx: usize) -> Vec<f32> {
+        result.casts.iter().filter(|(_, i)| *i == s_idx).map(|(t, _)| *t).collect()
+    }
+
+    fn no_aa(duration: f32) -> FightSettings {
+        FightSettings { duration, auto_attack: false, ..Default::default() }
+    }
+
+    fn assert_times(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} vs {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-3, "{actual:?} vs {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_cooldown_gates_recasts() {
+        let build = TestBuild::new([2000, 4000, 0, 0, 0], 0.0);
+        let rotation = [RotationAction::Skill(1)];
+        let result = Simulation::new(&build, &rotation, no_aa(10.0)).run(None);
+        assert_times(&cast_times(&result, 1), &[0.0, 4.0, 8.0]);
+    }
+
+    #[test]
+    fn test_rotation_skips_missing_skills() {
+        let build = TestBuild::new([2000, 4000, 0, 0, 0], 0.0);
+        let rotation = [RotationAction::Skill(9), RotationAction::Skill(1)];
+        let result = Simulation::new(&build, &rotation, no_aa(10.0)).run(None);
+        assert_times(&cast_times(&result, 1), &[0.0, 4.0, 8.0]);
+        assert!(Simulation::new(&build, &rotation[..1], no_aa(10.0)).run(None).casts.is_empty());
+    }
+
+    #[test]
+    fn test_mana_runs_out_and_comes_back() {
+        let mut build = TestBuild::new([1000, 0, 0, 0, 0], 0.0);
+        build.stats = SecondaryStats { hit_chance: 100.0, mp: 100, current_mp: 100, ..build.stats };
+        build.enemy.dodge = 0.0;
+        build.skills[1].0.mp = 50;
+        let rotation = [RotationAction::Skill(1)];
+
+        let starved = Simulation::new(&build, &rotation, no_aa(10.0)).run(None);
+        assert_eq!(starved.cast_counts[1], 2);
+        assert!(starved.mana_starved > 0.0);
+
+        // Auto attacks give 10 MP a swing
+        let settings = FightSettings { duration: 10.0, aa_mana: 10, ..Default::default() };
+        let regen = Simulation::new(&build, &rotation, settings).run(None);
+        assert!(regen.cast_counts[1] > 2);
+        assert!(regen.mana_history.iter().all(|(_, mp)| (0.0..=100.0).contains(mp)));
+
+        // A full refund on every landed cast never runs dry
+        build.skills[1].0.properties.mana_back = Some(50);
+        let refunded = Simulation::new(&build, &rotation, no_aa(10.0)).run(None);
+        assert_eq!(refunded.mana_starved, 0.0);
+        assert_times(&cast_times(&refunded, 1), &[0.0, 1.5, 3.0, 4.5, 6.0, 7.5, 9.0]);
+    }
+
+    #[test]
+    fn test_gcd_spaces_out_skills() {
+        let build = TestBuild::new([2000, 0, 0, 0, 0], 0.0);
+        let rotation = [RotationAction::Skill(1), RotationAction::Skill(2)];
+        let result = Simulation::new(&build, &rotation, no_aa(5.0)).run(None);
+        assert_times(&cast_times(&result, 1), &[0.0, 3.0]);
+        assert_times(&cast_times(&result, 2), &[1.5, 4.5]);
+    }
+
+    #[test]
+    fn test_auto_attack_ignores_gcd() {
+        let build = TestBuild::new([2000, 0, 0, 0, 0], 0.0);
+        let rotation = [RotationAction::Skill(1)];
+        let settings = FightSettings { duration: 5.0, ..Default::default() };
+        let result = Simulation::new(&build, &rotation, settings).run(None);
+        assert_times(&cast_times(&result, 0), &[0.0, 2.0, 4.0]);
+        assert_times(&cast_times(&result, 1), &[0.0, 1.5, 3.0, 4.5]);
+    }
+
+    #[test]
+    fn test_haste_shortens_cooldown_and_gcd() {
+        // 20% haste: 1.2s GCD, a 4s cooldown becomes 3.2s
+        let build = TestBuild::new([2000, 4000, 0, 0, 0], 20.0);
+        let rotation = [RotationAction::Skill(1)];
+        let result = Simulation::new(&build, &rotation, no_aa(7.0)).run(None);
+        assert_times(&cast_times(&result, 1), &[0.0, 3.2, 6.4]);
+
+        let rotation = [RotationAction::Skill(2)];
+        let result = Simulation::new(&build, &rotation, no_aa(3.0)).run(None);
+        assert_times(&cast_times(&result, 2), &[0.0, 1.2, 2.4]);
+    }
+
+    #[test]
+    fn test_haste_is_capped() {
+        let build = TestBuild::new([2000, 0, 0, 0, 0], 80.0);
+        assert_eq!(haste_factor(&build.stats), 0.5);
+        let rotation = [RotationAction::Skill(1)];
+        let result = Simulation::new(&build, &rotation, no_aa(2.0)).run(None);
+        assert_times(&cast_times(&result, 1), &[0.0, 0.75, 1.5]);
+    }
+
+    #[test]
+    fn test_delay_holds_rotation() {
+        let build = TestBuild::new([2000, 0, 0, 0, 0], 0.0);
+        let rotation = [RotationAction::Skill(1), RotationAction::Delay(2.0)];
+        let result = Simulation::new(&build, &rotation, no_aa(5.0)).run(None);
+        assert_times(&cast_times(&result, 1), &[0.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn test_enemy_hits_go_through_player_mitigation() {
+        let hit = EnemySkill { damage: 100, cooldown: 1000, ..Default::default() };
+        let mut build = TestBuild::new([2000, 0, 0, 0, 0], 0.0);
+        build.enemy = EnemySecondaryStats { hit_chance: 100.0, ..Default::default() };
+        build.stats.dodge = 0.0;
+        build.enemy_skills = vec![(hit, vec![], false)];
+        let result = Simulation::new(&build, &[], no_aa(2.5)).run(None);
+        assert_eq!(result.player_hp, 700);
+
+        build.stats.phy_in = 50.0;
+        build.stats.dodge = 50.0;
+        let result = Simulation::new(&build, &[], no_aa(2.5)).run(None);
+        assert_eq!(result.player_hp, 925);
+    }
+
+    fn phase(trigger: PhaseTrigger) -> EnemyPhase {
+        EnemyPhase { name: "Next".into(), trigger, overrides: vec![], skills: vec![], invulnerable: 0.0 }
+    }
+
+    #[test]
+    fn test_timed_phase_and_invulnerability() {
+        let mut build = TestBuild::new([2000, 0, 0, 0, 0], 0.0);
+        let rotation = [RotationAction::Skill(1)];
+        let plain = Simulation::new(&build, &rotation, no_aa(5.0)).run(None);
+        assert_eq!(plain.phase_times, vec![("Opening".to_string(), 5.0)]);
+
+        // Casts at 0, 1.5, 3 and 4.5, the one at 3 lands while the enemy is invulnerable
+        build.enemy_phases = vec![EnemyPhase { invulnerable: 1.5, ..phase(PhaseTrigger::Time(2.0)) }];
+        let result = Simulation::new(&build, &rotation, no_aa(5.0)).run(None);
+        assert_eq!(result.phase_times, vec![("Opening".to_string(), 2.0), ("Next".to_string(), 3.0)]);
+        assert_eq!(result.cast_counts, plain.cast_counts);
+        assert!((result.total_dmg - plain.total_dmg * 0.75).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_hp_phase_swaps_stats_and_skills() {
+        let mut build = TestBuild::new([2000, 0, 0, 0, 0], 0.0);
+        let rotation = [RotationAction::Skill(1)];
+        let hit = Simulation::new(&build, &rotation, no_aa(0.5)).run(None).total_dmg;
+
+        let smash = EnemySkill { damage: 100, cooldown: 10000, force_result: Some(ForceResult::Hit), ..Default::default() };
+        build.enemy_phases = vec![EnemyPhase {
+            overrides: vec![(StatKey::AllIn, 0.0)],
+            skills: vec![(smash, vec![], false)],
+            ..phase(PhaseTrigger::HpBelow(50.0))
+        }];
+        // Two hits take it under half, after that it shrugs everything off and hits back once
+        let settings = FightSettings { enemy_hp: (hit * 3.0) as i32, ..no_aa(5.0) };
+        let result = Simulation::new(&build, &rotation, settings).run(None);
+        assert_eq!(result.phase_times[0], ("Opening".to_string(), 1.5));
+        assert!(!result.enemy_defeated);
+        assert!((result.total_dmg - hit * 2.0).abs() < 1e-2);
+        assert_eq!(result.player_hp, 900);
+    }
+
+    #[test]
+    fn test_priority_falls_through_cooldowns() {
+        let build = TestBuild::new([2000, 4000, 0, 0, 0], 0.0);
+        let list = parse_priority_list("2; 3").unwrap();
+        let result = Simulation::with_priority(&build, &list, no_aa(7.0)).run(None);
+        assert_times(&cast_times(&result, 1), &[0.0, 4.5]);
+        assert_times(&cast_times(&result, 2), &[1.5, 3.0, 6.0]);
+    }
+
+    #[test]
+    fn test_priority_refreshes_missing_buff() {
+        let mut build = TestBuild::new([2000, 0, 0, 0, 0], 0.0);
+        let buff = CustomPassive { target_type: TargetType::Secondary, stat_name: StatKey::AllOut, value: 10.0, duration: Some(3000), ..Default::default() };
+        build.skills[1].1 = vec![buff];
+        let list = parse_priority_list("2 if !buff(2)\n3").unwrap();
+        let result = Simulation::with_priority(&build, &list, no_aa(5.0)).run(None);
+        assert_times(&cast_times(&result, 1), &[0.0, 3.0]);
+        assert_times(&cast_times(&result, 2), &[1.5, 4.5]);
+    }
+}
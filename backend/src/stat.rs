@@ -0,0 +1,381 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
+use crate::{error::BackendError, passive::TargetType, player::{PrimaryStats, SecondaryStats}};
+
+/// Every stat a passive can change.
+///
+/// Saved as its display name, so builds saved when this was a free-form string still load.
+/// The extra `serialize` names are spellings older presets wrote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, EnumIter, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+#[strum(ascii_case_insensitive)]
+pub enum StatKey {
+    // --- Primary ---
+    Strength,
+    #[strum(to_string = "Intellect", serialize = "Intelligence")]
+    Intellect,
+    Endurance,
+    Dexterity,
+    Wisdom,
+    Luck,
+
+    // --- Secondary ---
+    Haste,
+    #[strum(to_string = "Crit Chance", serialize = "Critical Chance")]
+    CritChance,
+    #[strum(to_string = "Hit Chance")]
+    HitChance,
+    #[strum(to_string = "Dodge Chance", serialize = "Dodge", serialize = "Evasion")]
+    Dodge,
+    #[strum(to_string = "All Out")]
+    AllOut,
+    #[strum(to_string = "Phy Out")]
+    PhyOut,
+    #[strum(to_string = "Mag Out")]
+    MagOut,
+    #[strum(to_string = "Heal Out")]
+    HealOut,
+    #[strum(to_string = "All In")]
+    AllIn,
+    #[strum(to_string = "Phy In")]
+    PhyIn,
+    #[strum(to_string = "Mag In")]
+    MagIn,
+    #[strum(to_string = "Heal In")]
+    HealIn,
+    #[strum(to_string = "DoT In")]
+    DotIn,
+    #[strum(to_string = "DoT Out")]
+    DotOut,
+    #[strum(to_string = "Mana Consumption")]
+    ManaConsumption,
+    #[strum(to_string = "Attack Power")]
+    AttackPower,
+    #[strum(to_string = "Spell Power")]
+    SpellPower,
+    #[strum(to_string = "Crit Modifier")]
+    CritModifier,
+}
+
+impl StatKey {
+    pub fn is_primary(&self) -> bool {
+        matches!(self, StatKey::Strength | StatKey::Intellect | StatKey::Endurance | StatKey::Dexterity | StatKey::Wisdom | StatKey::Luck)
+    }
+
+    /// Stats an enemy has, everything but the primary stats and mana consumption.
+    /// Attack and spell power stay since enemy skills scale off them.
+    pub fn is_enemy(&self) -> bool {
+        !self.is_primary() && !matches!(self, StatKey::ManaConsumption)
+    }
+
+    /// Dropdown options for a passive aimed at `target`, in display order
+    pub fn options(target: &TargetType) -> Vec<StatKey> {
+        StatKey::iter()
+            .filter(|key| match target {
+                TargetType::Primary => key.is_primary(),
+                TargetType::Secondary => !key.is_primary(),
+                TargetType::Enemy => key.is_enemy(),
+            })
+            .collect()
+    }
+}
+
+impl From<StatKey> for String {
+    fn from(key: StatKey) -> Self {
+        key.to_string()
+    }
+}
+
+impl TryFrom<String> for StatKey {
+    type Error = BackendError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse().map_err(|e| BackendError::InvalidStatKey(format!("Error: {e} ")))
+    }
+}
+
+/// Stats that can be read and written by key.
+pub trait StatBlock {
+    /// `None` if this block doesn't have the stat
+    fn get(&self, key: StatKey) -> Option<f32>;
+    /// Does nothing if this block doesn't have the stat
+    fn set(&mut self, key: StatKey, value: f32);
+}
+
+impl StatBlock for PrimaryStats {
+    fn get(&self, key: StatKey) -> Option<f32> {
+        let value = match key {
+            StatKey::Strength => self.strength,
+            StatKey::Intellect => self.intellect,
+            StatKey::Endurance => self.endurance,
+            StatKey::Dexterity => self.dexterity,
+            StatKey::Wisdom => self.wisdom,
+            StatKey::Luck => self.luck,
+            _ => return None,
+        };
+        Some(value as f32)
+    }
+
+    // Primary stats are whole numbers, the layered result is rounded once at the end
+    fn set(&mut self, key: StatKey, value: f32) {
+        let value = value.round() as i32;
+        match key {
+            StatKey::Strength => self.strength = value,
+            StatKey::Intellect => self.intellect = value,
+            StatKey::Endurance => self.endurance = value,
+            StatKey::Dexterity => self.dexterity = value,
+            StatKey::Wisdom => self.wisdom = value,
+            StatKey::Luck => self.luck = value,
+            _ => (),
+        }
+    }
+}
+
+impl StatBlock for SecondaryStats {
+    fn get(&self, key: StatKey) -> Option<f32> {
+        let value = match key {
+            StatKey::Haste => self.haste,
+            StatKey::CritChance => self.crit_chance,
+            StatKey::HitChance => self.hit_chance,
+            StatKey::Dodge => self.dodge,
+            StatKey::AllOut => self.all_out,
+            StatKey::PhyOut => self.phy_out,
+            StatKey::MagOut => self.mag_out,
+            StatKey::HealOut => self.heal_out,
+            StatKey::AllIn => self.all_in,
+            StatKey::PhyIn => self.phy_in,
+            StatKey::MagIn => self.mag_in,
+            StatKey::HealIn => self.heal_in,
+            StatKey::DotIn => self.dot_in,
+            StatKey::DotOut => self.dot_out,
+            StatKey::ManaConsumption => self.mana_consumption,
+            StatKey::AttackPower => self.attack_power,
+            StatKey::SpellPower => self.spell_power,
+            StatKey::CritModifier => self.crit_mod,
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn set(&mut self, key: StatKey, value: f32) {
+        let field = match key {
+            StatKey::Haste => &mut self.haste,
+            StatKey::CritChance => &mut self.crit_chance,
+            StatKey::HitChance => &mut self.hit_chance,
+            StatKey::Dodge => &mut self.dodge,
+            StatKey::AllOut => &mut self.all_out,
+            StatKey::PhyOut => &mut self.phy_out,
+            StatKey::MagOut => &mut self.mag_out,
+            StatKey::HealOut => &mut self.heal_out,
+            StatKey::AllIn => &mut self.all_in,
+            StatKey::PhyIn => &mut self.phy_in,
+            StatKey::MagIn => &mut self.mag_in,
+            StatKey::HealIn => &mut self.heal_in,
+            StatKey::DotIn => &mut self.dot_in,
+            StatKey::DotOut => &mut self.dot_out,
+            StatKey::ManaConsumption => &mut self.mana_consumption,
+            StatKey::AttackPower => &mut self.attack_power,
+            StatKey::SpellPower => &mut self.spell_power,
+            StatKey::CritModifier => &mut self.crit_mod,
+            _ => return,
+        };
+        *field = value;
+    }
+}
+
+/// Range a stat is clamped to when it's used in a fight.
+/// Stored stats stay uncapped so the panel can show both values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatLimit {
+    pub floor: f32,
+    pub cap: f32,
+}
+
+impl StatLimit {
+    const fn new(floor: f32, cap: f32) -> Self {
+        StatLimit { floor, cap }
+    }
+
+    pub fn clamp(&self, value: f32) -> f32 {
+        value.clamp(self.floor, self.cap)
+    }
+}
+
+impl StatKey {
+    /// The caps and floors table, shared by the player and the enemy.
+    pub fn limit(&self) -> StatLimit {
+        match self {
+            // Primary stats only have to stay positive
+            StatKey::Strength | StatKey::Intellect | StatKey::Endurance
+            | StatKey::Dexterity | StatKey::Wisdom | StatKey::Luck => StatLimit::new(0.0, f32::INFINITY),
+            // AQW hard haste cap
+            StatKey::Haste => StatLimit::new(0.0, 50.0),
+            StatKey::CritChance | StatKey::Dodge => StatLimit::new(0.0, 100.0),
+            // Hit over 100 still offsets dodge, land_chance caps the result instead
+            StatKey::HitChance => StatLimit::new(0.0, f32::INFINITY),
+            // A crit never hits for less than a normal hit
+            StatKey::CritModifier => StatLimit::new(100.0, f32::INFINITY),
+            // Damage and healing never flip sign
+            StatKey::AllOut | StatKey::PhyOut | StatKey::MagOut | StatKey::HealOut | StatKey::DotOut
+            | StatKey::AllIn | StatKey::PhyIn | StatKey::MagIn | StatKey::HealIn | StatKey::DotIn => StatLimit::new(0.0, f32::INFINITY),
+            // Skills can get free, not refund MP
+            StatKey::ManaConsumption => StatLimit::new(0.0, f32::INFINITY),
+            StatKey::AttackPower | StatKey::SpellPower => StatLimit::new(0.0, f32::INFINITY),
+        }
+    }
+
+    pub fn clamp(&self, value: f32) -> f32 {
+        self.limit().clamp(value)
+    }
+}
+
+/// `stats` with every stat it has clamped to its limit.
+pub fn capped<S: StatBlock + Clone>(stats: &S) -> S {
+    let mut result = stats.clone();
+    for key in StatKey::iter() {
+        if let Some(value) = stats.get(key) {
+            result.set(key, key.clamp(value));
+        }
+    }
+    result
+}
+
+/// Order modifiers are applied in. Inside a layer the order doesn't matter:
+///
+/// `value = (base + Σ additive) × Π multiplicative + Σ final`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModifierLayer {
+    /// The stat before any passive, from level, gear and class conversion
+    Base,
+    Additive,
+    Multiplicative,
+    /// Flat amounts added after every multiplier
+    Final,
+}
+
+/// How a stat got from its base value to its final value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatDerivation {
+    pub key: StatKey,
+    pub base: f32,
+    /// Signed amounts and factors, in the order they were added
+    pub modifiers: Vec<(ModifierLayer, f32)>,
+}
+
+impl StatDerivation {
+    pub fn new(key: StatKey, base: f32) -> Self {
+        StatDerivation { key, base, modifiers: Vec::new() }
+    }
+
+    pub fn push(&mut self, layer: ModifierLayer, value: f32) {
+        self.modifiers.push((layer, value));
+    }
+
+    pub fn is_modified(&self) -> bool {
+        !self.modifiers.is_empty()
+    }
+
+    fn layer(&self, layer: ModifierLayer) -> impl Iterator<Item = f32> + '_ {
+        self.modifiers.iter().filter(move |(l, _)| *l == layer).map(|(_, v)| *v)
+    }
+
+    pub fn additive(&self) -> f32 {
+        self.layer(ModifierLayer::Additive).sum()
+    }
+
+    pub fn multiplier(&self) -> f32 {
+        self.layer(ModifierLayer::Multiplicative).product()
+    }
+
+    pub fn flat(&self) -> f32 {
+        self.layer(ModifierLayer::Final).sum()
+    }
+
+    pub fn value(&self) -> f32 {
+        (self.base + self.additive()) * self.multiplier() + self.flat()
+    }
+}
+
+impl fmt::Display for StatDerivation {
+    // e.g. "Haste: 20.00 + 30.00 × 1.20 = 60.00", layers with nothing in them are left out
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:.2}", self.key, self.base)?;
+        let additive = self.additive();
+        if additive != 0.0 {
+            write!(f, " {} {:.2}", if additive < 0.0 { "-" } else { "+" }, additive.abs())?;
+        }
+        if self.layer(ModifierLayer::Multiplicative).next().is_some() {
+            if additive != 0.0 {
+                write!(f, " = {:.2}", self.base + additive)?;
+            }
+            write!(f, " × {:.2}", self.multiplier())?;
+        }
+        let flat = self.flat();
+        if flat != 0.0 {
+            write!(f, " {} {:.2}", if flat < 0.0 { "-" } else { "+" }, flat.abs())?;
+        }
+        if self.is_modified() {
+            write!(f, " = {:.2}", self.value())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::enemy::EnemySecondaryStats;
+
+    #[test]
+    fn test_stat_key_names_round_trip() {
+        for key in StatKey::iter() {
+            assert_eq!(StatKey::from_str(&key.to_string()).unwrap(), key);
+        }
+        assert_eq!(StatKey::DotIn.to_string(), "DoT In");
+        assert!(StatKey::from_str("Dodgy").is_err());
+    }
+
+    #[test]
+    fn test_stat_key_loads_old_names() {
+        let keys: Vec<StatKey> = serde_json::from_str(r#"["Dodge", "Dodge Chance", "Evasion", "Intelligence", "phy out"]"#).unwrap();
+        assert_eq!(keys, vec![StatKey::Dodge, StatKey::Dodge, StatKey::Dodge, StatKey::Intellect, StatKey::PhyOut]);
+        assert_eq!(serde_json::to_string(&StatKey::CritChance).unwrap(), "\"Crit Chance\"");
+        assert!(serde_json::from_str::<StatKey>("\"Typo\"").is_err());
+    }
+
+    #[test]
+    fn test_caps_apply_to_player_and_enemy() {
+        let stats = SecondaryStats { haste: 80.0, crit_chance: 120.0, mana_consumption: -30.0, crit_mod: 50.0, ..Default::default() };
+        let capped_stats = capped(&stats);
+        assert_eq!(capped_stats.haste, 50.0);
+        assert_eq!(capped_stats.crit_chance, 100.0);
+        assert_eq!(capped_stats.mana_consumption, 0.0);
+        assert_eq!(capped_stats.crit_mod, 100.0);
+        assert_eq!(capped_stats.all_out, stats.all_out);
+
+        let enemy = EnemySecondaryStats { haste: 80.0, dodge: -5.0, ..Default::default() };
+        let capped_enemy = capped(&enemy);
+        assert_eq!(capped_enemy.haste, 50.0);
+        assert_eq!(capped_enemy.dodge, 0.0);
+    }
+
+    #[test]
+    fn test_hit_over_100_offsets_dodge() {
+        let stats = capped(&SecondaryStats { hit_chance: 118.6, ..Default::default() });
+        assert_eq!(stats.hit_chance, 118.6);
+        assert_eq!(crate::damage::land_chance(stats.hit_chance, 10.0), 1.0);
+        assert!((crate::damage::land_chance(stats.hit_chance, 25.0) - 0.936).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_stat_key_options() {
+        assert_eq!(StatKey::options(&TargetType::Primary).len(), 6);
+        assert_eq!(StatKey::options(&TargetType::Secondary).len(), 18);
+        let enemy = StatKey::options(&TargetType::Enemy);
+        assert!(enemy.contains(&StatKey::Dodge));
+        assert!(enemy.contains(&StatKey::AttackPower) && enemy.contains(&StatKey::SpellPower));
+        assert!(!enemy.contains(&StatKey::ManaConsumption));
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use crate::{
+    damage::{Type, land_chance},
+    enemy::{EnemySecondaryStats, EnemySkill},
+    passive::CustomPassive,
+    player::SecondaryStats,
+    sim::{Loadout, SimResult},
+    stat::capped,
+};
+
+/// How long a player lasts against the build's enemy.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Survival {
+    /// Raw damage of each type it takes to kill the player from full HP, dodge included
+    pub ehp_physical: f32,
+    pub ehp_magical: f32,
+    /// Damage taken per second
+    pub dtps: f32,
+    /// Healing received per second, overheal not included
+    pub hps: f32,
+    /// Seconds until the player dies, `None` when healing keeps up
+    pub time_to_death: Option<f32>,
+}
+
+/// Raw `damage_type` damage the player can take before dying: max HP through their
+/// In modifiers and the chance the enemy's hits land at all. Infinite if nothing gets through.
+pub fn effective_hp(player: &SecondaryStats, enemy: &EnemySecondaryStats, damage_type: &Type) -> f32 {
+    let taken = player.incoming_modifier(damage_type) * land_chance(enemy.hit_chance, player.dodge);
+    if taken <= 0.0 { f32::INFINITY } else { player.hp as f32 / taken }
+}
+
+/// The weaker of physical and magical effective HP.
+pub fn min_effective_hp(player: &SecondaryStats, enemy: &EnemySecondaryStats) -> f32 {
+    effective_hp(player, enemy, &Type::Physical).min(effective_hp(player, enemy, &Type::Magical))
+}
+
+/// Damage per second the enemy's skills deal to the player on average, without running a fight.
+pub fn expected_dtps(player: &SecondaryStats, enemy: &EnemySecondaryStats, skills: &[(EnemySkill, Vec<CustomPassive>, bool)]) -> f32 {
+    skills.iter()
+        .filter(|(skill, _, _)| skill.hits_player())
+        .map(|(skill, _, can_crit)| skill.expected(enemy, player, *can_crit) / (skill.cooldown as f32 / 1000.0).max(0.001))
+        .sum()
+}
+
+impl Survival {
+    /// Survival numbers for `player` after the fight in `result`. A player who survived the fight
+    /// is extrapolated from the HP they had left and their net damage taken.
+    pub fn new(player: &SecondaryStats, enemy: &EnemySecondaryStats, result: &SimResult) -> Self {
+        let net = result.dtps - result.hps;
+        let time_to_death = if result.player_died {
+            Some(result.time)
+        } else if net > 0.0 {
+            Some(result.time + result.player_hp.max(0) as f32 / net)
+        } else {
+            None
+        };
+        Survival {
+            ehp_physical: effective_hp(player, enemy, &Type::Physical),
+            ehp_magical: effective_hp(player, enemy, &Type::Magical),
+            dtps: result.dtps,
+            hps: result.hps,
+            time_to_death,
+        }
+    }
+
+    /// Same as `new` with the build's unbuffed stats against its (capped) enemy.
+    pub fn of(build: &dyn Loadout, result: &SimResult) -> Self {
+        Survival::new(&build.stat_layers(&[]).dynamic_stats, &capped(build.enemy()), result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build::Build, sim::{FightSettings, Simulation}};
+
+    #[test]
+    fn test_effective_hp() {
+        let enemy = EnemySecondaryStats { hit_chance: 100.0, ..Default::default() };
+        let player = SecondaryStats { hp: 10000, dodge: 20.0, phy_in: 50.0, ..Default::default() };
+        // Half damage and 80% of hits landing
+        assert!((effective_hp(&player, &enemy, &Type::Physical) - 25000.0).abs() < 1e-2);
+        assert!((effective_hp(&player, &enemy, &Type::Magical) - 12500.0).abs() < 1e-2);
+        let untouchable = SecondaryStats { dodge: 100.0, ..player };
+        assert_eq!(effective_hp(&untouchable, &enemy, &Type::Physical), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_expected_dtps() {
+        let enemy = EnemySecondaryStats { hit_chance: 100.0, crit_chance: 0.0, ..Default::default() };
+        let player = SecondaryStats { dodge: 0.0, ..Default::default() };
+        let hit = EnemySkill { damage: 300, cooldown: 2000, ..Default::default() };
+        let heal = EnemySkill { damage_type: Type::Heal, target: crate::damage::Target::Yourself, ..hit.clone() };
+        assert_eq!(expected_dtps(&player, &enemy, &[(hit, vec![], true), (heal, vec![], false)]), 150.0);
+    }
+
+    #[test]
+    fn test_time_to_death() {
+        let mut build = Build {
+            enemy: EnemySecondaryStats { hit_chance: 100.0, crit_chance: 0.0, ..Default::default() },
+            enemy_skills: vec![(EnemySkill { damage: 200, cooldown: 1000, ..Default::default() }, vec![], false)],
+            ..Default::default()
+        };
+        let player = build.stat_layers(&[]).dynamic_stats;
+        let hit = 200.0 * player.incoming_modifier(&Type::Physical) * land_chance(100.0, player.dodge);
+        let fight = FightSettings { duration: 2.5, auto_attack: false, ..Default::default() };
+
+        // Three hits in 2.5s, whatever HP is left goes at the same rate
+        let result = Simulation::new(&build, &[], fight.clone()).run(None);
+        let survival = Survival::of(&build, &result);
+        assert!((survival.dtps - hit * 3.0 / 2.5).abs() < 1e-2);
+        assert!(!result.player_died);
+        let expected = 2.5 + result.player_hp as f32 / survival.dtps;
+        assert!((survival.time_to_death.unwrap() - expected).abs() < 1e-2);
+
+        build.enemy_skills.clear();
+        let result = Simulation::new(&build, &[], fight).run(None);
+        assert_eq!(Survival::of(&build, &result).time_to_death, None);
+    }
+}
@@ -0,0 +1,267 @@
+use std::fmt::{self, Display};
+use serde::{Deserialize, Serialize};
+use crate::{
+    build::Build,
+    damage::WeaponBoost,
+    error::BackendError,
+    passive::{CustomPassive, TargetType},
+    sim::{FightSettings, RotationAction, Simulation},
+    stat::{StatBlock, StatKey, StatLimit},
+};
+
+/// Most fights one sweep will run, a 50 by 50 grid
+pub const MAX_POINTS: usize = 2500;
+
+/// A number a sweep can vary.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SweepParam {
+    /// A secondary stat, held at the swept value by a flat bonus on top of the build
+    Secondary(StatKey),
+    /// A `WeaponBoost::Custom` multiplier
+    WeaponBoost,
+    WeaponDps,
+    Level,
+    EnemyAllIn,
+}
+
+impl SweepParam {
+    /// Everything that can be swept, in dropdown order
+    pub fn options() -> Vec<SweepParam> {
+        StatKey::options(&TargetType::Secondary).into_iter()
+            .map(SweepParam::Secondary)
+            .chain([SweepParam::WeaponBoost, SweepParam::WeaponDps, SweepParam::Level, SweepParam::EnemyAllIn])
+            .collect()
+    }
+
+    /// The build's own value, where a sweep usually starts
+    pub fn current(&self, build: &Build) -> f32 {
+        match self {
+            SweepParam::Secondary(key) => build.secondary_stats.get(*key).unwrap_or(0.0),
+            SweepParam::WeaponBoost => build.weapon.boost.multiplier(),
+            SweepParam::WeaponDps => build.weapon.dps,
+            SweepParam::Level => build.level.level as f32,
+            SweepParam::EnemyAllIn => build.enemy.all_in,
+        }
+    }
+}
+
+impl SweepParam {
+    /// The range the caps table lets this parameter take, `None` when it has no caps
+    pub fn limit(&self) -> Option<StatLimit> {
+        match self {
+            SweepParam::Secondary(key) => Some(key.limit()),
+            SweepParam::EnemyAllIn => Some(StatKey::AllIn.limit()),
+            SweepParam::WeaponBoost | SweepParam::WeaponDps | SweepParam::Level => None,
+        }
+    }
+}
+
+impl Display for SweepParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SweepParam::Secondary(key) => write!(f, "{key}"),
+            SweepParam::WeaponBoost => write!(f, "Weapon Boost"),
+            SweepParam::WeaponDps => write!(f, "Weapon DPS"),
+            SweepParam::Level => write!(f, "Level"),
+            SweepParam::EnemyAllIn => write!(f, "Enemy All In"),
+        }
+    }
+}
+
+/// One swept parameter and the values it takes, `steps` of them evenly from `from` to `to`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SweepAxis {
+    pub param: SweepParam,
+    pub from: f32,
+    pub to: f32,
+    pub steps: usize,
+}
+
+impl SweepAxis {
+    pub fn values(&self) -> Vec<f32> {
+        if self.steps <= 1 {
+            return vec![self.from];
+        }
+        let gap = (self.to - self.from) / (self.steps - 1) as f32;
+        (0..self.steps).map(|i| self.from + gap * i as f32).collect()
+    }
+}
+
+/// One fight of a sweep.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SweepPoint {
+    pub x: f32,
+    /// `None` when only one parameter was swept
+    pub y: Option<f32>,
+    pub dps: f32,
+    pub time_to_kill: Option<f32>,
+}
+
+/// Every fight of a sweep, row by row: all of `x` for the first `y`, then the next `y`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Sweep {
+    pub x: SweepAxis,
+    pub y: Option<SweepAxis>,
+    pub points: Vec<SweepPoint>,
+}
+
+impl Sweep {
+    /// One line per fight, a header naming the swept parameters first.
+    /// A fight that didn't kill the enemy leaves time to kill empty.
+    pub fn to_csv(&self) -> String {
+        let mut header = vec![self.x.param.to_string()];
+        header.extend(self.y.map(|y| y.param.to_string()));
+        header.extend(["DPS".to_string(), "Time to Kill".to_string()]);
+        let mut lines = vec![header.join(",")];
+        for point in &self.points {
+            let mut row = vec![point.x.to_string()];
+            row.extend(point.y.map(|y| y.to_string()));
+            row.push(point.dps.to_string());
+            row.push(point.time_to_kill.map(|t| t.to_string()).unwrap_or_default());
+            lines.push(row.join(","));
+        }
+        lines.join("\n")
+    }
+}
+
+/// `build` with every parameter set to its value.
+fn with_values(build: &Build, values: &[(SweepParam, f32)]) -> Build {
+    let mut build = build.clone();
+    // What secondary stats come from goes first, so a swept secondary stat lands on its value
+    for &(param, value) in values {
+        match param {
+            SweepParam::WeaponBoost => build.weapon.boost = WeaponBoost::Custom(value),
+            SweepParam::WeaponDps => build.weapon.dps = value,
+            SweepParam::Level => build.level.level = value.round().max(1.0) as u32,
+            SweepParam::EnemyAllIn => build.enemy.all_in = value,
+            SweepParam::Secondary(_) => (),
+        }
+    }
+    build.refresh_stats();
+    for &(param, value) in values {
+        if let SweepParam::Secondary(key) = param {
+            let current = build.secondary_stats.get(key).unwrap_or(0.0);
+            build.passives.push(CustomPassive::raising(TargetType::Secondary, key, value - current));
+            build.refresh_stats();
+        }
+    }
+    build
+}
+
+fn check_axis(axis: &SweepAxis) -> Result<(), BackendError> {
+    if axis.steps == 0 {
+        return Err(BackendError::InvalidSweep(format!("Error: {} needs at least one step ", axis.param)));
+    }
+    if !axis.from.is_finite() || !axis.to.is_finite() {
+        return Err(BackendError::InvalidSweep(format!("Error: {} range isn't a number ", axis.param)));
+    }
+    if let SweepParam::Secondary(key) = axis.param && key.is_primary() {
+        return Err(BackendError::InvalidSweep(format!("Error: {key} is a primary stat ")));
+    }
+    // Past a cap the fight runs at the capped value, so the point would be mislabelled
+    if let Some(limit) = axis.param.limit() && [axis.from, axis.to].iter().any(|v| limit.clamp(*v) != *v) {
+        return Err(BackendError::InvalidSweep(format!("Error: {} goes from {} to {} ", axis.param, limit.floor, limit.cap)));
+    }
+    Ok(())
+}
+
+/// Runs the fight in `settings` with `rotation` at every point of `x`, or of the `x` by `y` grid.
+pub fn sweep(build: &Build, settings: &FightSettings, rotation: &[RotationAction], x: SweepAxis, y: Option<SweepAxis>) -> Result<Sweep, BackendError> {
+    check_axis(&x)?;
+    if let Some(y) = &y {
+        check_axis(y)?;
+        if y.param == x.param {
+            return Err(BackendError::InvalidSweep(format!("Error: {} is swept twice ", x.param)));
+        }
+    }
+    let ys: Vec<Option<f32>> = match &y {
+        Some(y) => y.values().into_iter().map(Some).collect(),
+        None => vec![None],
+    };
+    let points = x.steps.saturating_mul(ys.len());
+    if points > MAX_POINTS {
+        return Err(BackendError::InvalidSweep(format!("Error: {points} fights, at most {MAX_POINTS} ")));
+    }
+
+    let points = ys.iter()
+        .flat_map(|&y_value| x.values().into_iter().map(move |x_value| (x_value, y_value)))
+        .map(|(x_value, y_value)| {
+            let mut values = vec![(x.param, x_value)];
+            values.extend(y.zip(y_value).map(|(axis, value)| (axis.param, value)));
+            let result = Simulation::new(&with_values(build, &values), rotation, settings.clone()).run(None);
+            SweepPoint { x: x_value, y: y_value, dps: result.dps, time_to_kill: result.enemy_defeated.then_some(result.time) }
+        })
+        .collect();
+    Ok(Sweep { x, y, points })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fight() -> FightSettings {
+        FightSettings { duration: 10.0, ..Default::default() }
+    }
+
+    #[test]
+    fn test_axis_values() {
+        let axis = SweepAxis { param: SweepParam::WeaponDps, from: 1.5, to: 2.5, steps: 5 };
+        assert_eq!(axis.values(), vec![1.5, 1.75, 2.0, 2.25, 2.5]);
+        assert_eq!(SweepAxis { steps: 1, ..axis }.values(), vec![1.5]);
+    }
+
+    #[test]
+    fn test_weapon_dps_sweep() {
+        let x = SweepAxis { param: SweepParam::WeaponDps, from: 50.0, to: 150.0, steps: 3 };
+        let result = sweep(&Build::default(), &fight(), &[], x, None).unwrap();
+        assert_eq!(result.points.len(), 3);
+        assert!(result.points.windows(2).all(|w| w[1].dps > w[0].dps));
+    }
+
+    #[test]
+    fn test_secondary_stat_lands_on_value() {
+        let build = with_values(&Build::default(), &[(SweepParam::Level, 50.0), (SweepParam::Secondary(StatKey::Haste), 20.0)]);
+        assert_eq!(build.level.level, 50);
+        assert!((build.secondary_stats.haste - 20.0).abs() < 1e-3);
+        assert!((SweepParam::Secondary(StatKey::Haste).current(&build) - 20.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_in_stat_lands_on_value() {
+        let build = with_values(&Build::default(), &[(SweepParam::Secondary(StatKey::AllIn), 110.0)]);
+        assert!((build.secondary_stats.all_in - 110.0).abs() < 1e-3);
+        let build = with_values(&Build::default(), &[(SweepParam::Secondary(StatKey::PhyIn), 70.0)]);
+        assert!((build.secondary_stats.phy_in - 70.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_grid_and_csv() {
+        let x = SweepAxis { param: SweepParam::WeaponBoost, from: 1.5, to: 2.5, steps: 3 };
+        let y = SweepAxis { param: SweepParam::EnemyAllIn, from: 0.0, to: 20.0, steps: 2 };
+        let result = sweep(&Build::default(), &FightSettings { enemy_hp: 1000, ..fight() }, &[], x, Some(y)).unwrap();
+        assert_eq!(result.points.len(), 6);
+        assert_eq!((result.points[3].x, result.points[3].y), (1.5, Some(20.0)));
+
+        let csv = result.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "Weapon Boost,Enemy All In,DPS,Time to Kill");
+        assert_eq!(lines.len(), 7);
+        assert!(lines[1].starts_with("1.5,0,"));
+    }
+
+    #[test]
+    fn test_bad_sweeps() {
+        let x = SweepAxis { param: SweepParam::Level, from: 1.0, to: 100.0, steps: 100 };
+        let build = Build::default();
+        assert!(sweep(&build, &fight(), &[], SweepAxis { steps: 0, ..x }, None).is_err());
+        assert!(sweep(&build, &fight(), &[], SweepAxis { to: f32::NAN, ..x }, None).is_err());
+        assert!(sweep(&build, &fight(), &[], SweepAxis { param: SweepParam::Secondary(StatKey::Luck), ..x }, None).is_err());
+        // Haste caps at 50 and In stats can't go negative
+        let haste = SweepAxis { param: SweepParam::Secondary(StatKey::Haste), from: 40.0, to: 60.0, steps: 3 };
+        assert!(sweep(&build, &fight(), &[], haste, None).is_err());
+        assert!(sweep(&build, &fight(), &[], SweepAxis { to: 50.0, ..haste }, None).is_ok());
+        assert!(sweep(&build, &fight(), &[], SweepAxis { param: SweepParam::EnemyAllIn, from: -10.0, ..haste }, None).is_err());
+        assert!(sweep(&build, &fight(), &[], x, Some(x)).is_err());
+        assert!(sweep(&build, &fight(), &[], x, Some(SweepAxis { param: SweepParam::WeaponDps, ..x })).is_err());
+    }
+}
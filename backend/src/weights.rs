@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use crate::{
+    build::Build,
+    passive::{CustomPassive, TargetType},
+    sim::{FightSettings, RotationAction, Simulation},
+    stat::{StatKey, capped},
+    survival::min_effective_hp,
+};
+
+/// What one more point of a stat is worth to a build.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct StatWeight {
+    pub key: StatKey,
+    /// DPS gained per point
+    pub dps: f32,
+    /// Effective HP gained per point, the weaker of physical and magical
+    pub ehp: f32,
+}
+
+/// How far each stat is nudged. Primary stats and powers move in tens so the
+/// rounding in the secondary conversion doesn't swallow the change.
+fn step(key: StatKey) -> f32 {
+    match key {
+        StatKey::AttackPower | StatKey::SpellPower => 10.0,
+        key if key.is_primary() => 10.0,
+        _ => 1.0,
+    }
+}
+
+/// DPS and effective HP of `build` in one fight.
+fn measure(build: &Build, settings: &FightSettings, rotation: &[RotationAction]) -> (f32, f32) {
+    let result = Simulation::new(build, rotation, settings.clone()).run(None);
+    (result.dps, min_effective_hp(&build.stat_layers(&[]).dynamic_stats, &capped(&build.enemy)))
+}
+
+/// Change per point, zero when either side is infinite (a stat that makes the player untouchable)
+fn per_point(before: f32, after: f32, step: f32) -> f32 {
+    let diff = (after - before) / step;
+    if diff.is_finite() { diff } else { 0.0 }
+}
+
+/// DPS and EHP weights for every primary stat and secondary field.
+///
+/// Each stat gets a permanent flat bonus on top of the build, so primary stats go through
+/// the class model's secondary conversion and everything runs through caps and the sim,
+/// just like a real passive would. The bonus raises the field the stat panel shows, so a
+/// point of All In is a point more damage taken.
+pub fn stat_weights(build: &Build, settings: &FightSettings, rotation: &[RotationAction]) -> Vec<StatWeight> {
+    let (base_dps, base_ehp) = measure(build, settings, rotation);
+    StatKey::iter()
+        .map(|key| {
+            let mut nudged = build.clone();
+            let target = if key.is_primary() { TargetType::Primary } else { TargetType::Secondary };
+            nudged.passives.push(CustomPassive::raising(target, key, step(key)));
+            nudged.refresh_stats();
+            let (dps, ehp) = measure(&nudged, settings, rotation);
+            StatWeight { key, dps: per_point(base_dps, dps, step(key)), ehp: per_point(base_ehp, ehp, step(key)) }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weights(build: &Build) -> Vec<StatWeight> {
+        let settings = FightSettings { duration: 10.0, ..Default::default() };
+        stat_weights(build, &settings, &[RotationAction::Skill(1)])
+    }
+
+    fn weight(weights: &[StatWeight], key: StatKey) -> StatWeight {
+        *weights.iter().find(|w| w.key == key).unwrap()
+    }
+
+    #[test]
+    fn test_every_stat_weighed() {
+        let weights = weights(&Build::default());
+        assert_eq!(weights.len(), StatKey::iter().count());
+        // More damage out is more DPS, more endurance is more HP
+        assert!(weight(&weights, StatKey::AllOut).dps > 0.0);
+        assert!(weight(&weights, StatKey::Endurance).ehp > 0.0);
+        assert_eq!(weight(&weights, StatKey::AllOut).ehp, 0.0);
+        // A higher In value is more damage taken
+        assert!(weight(&weights, StatKey::AllIn).ehp < 0.0);
+        assert!(weight(&weights, StatKey::PhyIn).ehp < 0.0);
+    }
+
+    #[test]
+    fn test_capped_stat_is_worthless() {
+        let mut build = Build::default();
+        build.passives.push(CustomPassive {
+            target_type: TargetType::Secondary,
+            stat_name: StatKey::CritChance,
+            value: 500.0,
+            ..Default::default()
+        });
+        build.refresh_stats();
+        assert_eq!(weight(&weights(&build), StatKey::CritChance).dps, 0.0);
+    }
+}
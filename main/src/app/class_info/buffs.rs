@@ -1,12 +1,12 @@
 use yew::prelude::*;
-use crate::app::class_info::class::{ClassSettings};
-use crate::app::class_info::passive::{CustomPassive, TargetType, OperationType};
+use backend::build::Build;
+use backend::{passive::{CustomPassive, TargetType, OperationType}, stat::StatKey};
 use crate::push_passives;
 
 
 #[derive(Properties, PartialEq)]
 pub struct BuffProps {
-    pub settings: ClassSettings,
+    pub settings: Build,
     pub on_update_passives: Callback<Vec<CustomPassive>>,
 }
 
@@ -59,11 +59,10 @@ pub fn buff_manager(props: &BuffProps) -> Html {
 
         match val.as_str() {
             "Empowerment" => {
-                let stats = ["Strength", "Intellect", "Endurance", "Dexterity", "Wisdom", "Luck"];
-                for s in stats {
+                for stat_name in StatKey::options(&TargetType::Primary) {
                     list.push(CustomPassive {
                         target_type: TargetType::Primary,
-                        stat_name: s.to_string(),
+                        stat_name,
                         value: 1.2,
                         operation_type: OperationType::Multiplicative,
                         duration: None,
@@ -72,166 +71,166 @@ pub fn buff_manager(props: &BuffProps) -> Html {
             },
             "Resurgence" => {
                 push_passives!(list,
-                    Secondary, "All Out" => 30.0;
-                    Secondary, "All In" => 30.0;
+                    Secondary, AllOut => 30.0;
+                    Secondary, AllIn => 30.0;
                 );
                 
             },
             "Clarity" => {
                 push_passives!(list,
-                    Secondary, "Haste" => 30.0;
-                    Secondary, "Hit Chance" => 30.0;
-                    Secondary, "Crit Modifier" => 30.0;
+                    Secondary, Haste => 30.0;
+                    Secondary, HitChance => 30.0;
+                    Secondary, CritModifier => 30.0;
                 );
             },
             "Depravity" => {
                 push_passives!(list,
-                    Secondary, "All Out" => 30.0;
-                    Secondary, "Crit Chance" => 30.0;
-                    Secondary, "Dodge Chance" => 30.0;
-                    Secondary, "Haste" => 20.0;
-                    Secondary, "Crit Modifier" => 30.0;
+                    Secondary, AllOut => 30.0;
+                    Secondary, CritChance => 30.0;
+                    Secondary, Dodge => 30.0;
+                    Secondary, Haste => 20.0;
+                    Secondary, CritModifier => 30.0;
                 );
             },
             "Clarity Cordial" => {
                 push_passives!(list,
-                    Primary, "Wisdom" => 1.5, Multi;
+                    Primary, Wisdom => 1.5, Multi;
                 );
             },
             "Dragonheart Philtre" => {
                 push_passives!(list,
-                    Secondary, "Mana Consumption" => -50.0; 
+                    Secondary, ManaConsumption => -50.0; 
                 );
             },
             "Endurance Draught" => {
                 push_passives!(list,
-                    Primary, "Endurance" => 1.5, Multi;
+                    Primary, Endurance => 1.5, Multi;
                 );
             },
             "Felicitous Philtre" => {
                 push_passives!(list,
-                    Primary, "Luck" => 1.5, Multi;
+                    Primary, Luck => 1.5, Multi;
                 );
             },
             "Fleet Foot Philtre" => {
                 push_passives!(list,
-                    Secondary, "Haste" => 15.0;
-                    Secondary, "Evasion" => 50.0;
+                    Secondary, Haste => 15.0;
+                    Secondary, Dodge => 50.0;
                 );
             },
             "Honor Potion" => {
                 push_passives!(list,
-                    Secondary, "All Out" => 50.0;
-                    Secondary, "Mana Consumption" => 15.0;
+                    Secondary, AllOut => 50.0;
+                    Secondary, ManaConsumption => 15.0;
                 );
             },
             "Potent Honor Potion" => {
                 push_passives!(list,
-                    Secondary, "All Out" => 75.0;
-                    Secondary, "Mana Consumption" => 25.0;
+                    Secondary, AllOut => 75.0;
+                    Secondary, ManaConsumption => 25.0;
                 );
             },
             "Body Tonic" => {
                 push_passives!(list,
-                    Primary, "Endurance" => 1.2, Multi;
+                    Primary, Endurance => 1.2, Multi;
                 );
             },
             "Fate Tonic" => {
                 push_passives!(list,
-                    Primary, "Luck" => 1.2, Multi;
+                    Primary, Luck => 1.2, Multi;
                 );
             },
             "Mastery Tonic" => {
                 push_passives!(list,
-                    Primary, "Dexterity" => 1.2, Multi;
+                    Primary, Dexterity => 1.2, Multi;
                 );
             },
             "Might Tonic" => {
                 push_passives!(list,
-                    Primary, "Strength" => 1.2, Multi;
+                    Primary, Strength => 1.2, Multi;
                 );
             },
             "Wise Tonic" => {
                 push_passives!(list,
-                    Primary, "Wisdom" => 1.2, Multi;
+                    Primary, Wisdom => 1.2, Multi;
                 );
             },
             "Sage Tonic" => {
                 push_passives!(list,
-                    Primary, "Intelligence" => 1.2, Multi;
+                    Primary, Intellect => 1.2, Multi;
                 );
             },
             "Unstable Body Tonic" => {
                 push_passives!(list,
-                    Primary, "Endurance" => 1.22, Multi;
-                    Primary, "Luck" => 0.9, Multi;
+                    Primary, Endurance => 1.22, Multi;
+                    Primary, Luck => 0.9, Multi;
                 );
             },
             "Unstable Fate Tonic" => {
                 push_passives!(list,
-                    Primary, "Luck" => 1.22, Multi;
-                    Primary, "Endurance" => 0.9, Multi;
+                    Primary, Luck => 1.22, Multi;
+                    Primary, Endurance => 0.9, Multi;
                 );
             },
             "Unstable Mastery Tonic" => {
                 push_passives!(list,
-                    Primary, "Dexterity" => 1.22, Multi;
-                    Primary, "Endurance" => 0.9, Multi;
+                    Primary, Dexterity => 1.22, Multi;
+                    Primary, Endurance => 0.9, Multi;
                 );
             },
             "Unstable Might Tonic" => {
                 push_passives!(list,
-                    Primary, "Strength" => 1.22, Multi;
-                    Primary, "Endurance" => 0.9, Multi;
+                    Primary, Strength => 1.22, Multi;
+                    Primary, Endurance => 0.9, Multi;
                 );
             },
             "Unstable Sage Tonic" => {
                 push_passives!(list,
-                    Primary, "Intelligence" => 1.22, Multi;
-                    Primary, "Endurance" => 0.9, Multi;
+                    Primary, Intellect => 1.22, Multi;
+                    Primary, Endurance => 0.9, Multi;
                 );
             },
             "Unstable Wise Tonic" => {
                 push_passives!(list,
-                    Primary, "Wisdom" => 1.22, Multi;
-                    Primary, "Endurance" => 0.9, Multi;
+                    Primary, Wisdom => 1.22, Multi;
+                    Primary, Endurance => 0.9, Multi;
                 );
             },
             "Destruction Elixir" => {
                 push_passives!(list,
-                    Secondary, "Crit Modifier" => 30.0;
+                    Secondary, CritModifier => 30.0;
                 );
             },
             "Divine Elixir" => {
                 push_passives!(list,
-                    Secondary, "Heal Out" => 35.0;
+                    Secondary, HealOut => 35.0;
                 );
             },
             "Potent Battle Elixir" => {
                 push_passives!(list,
-                    Secondary, "Phy Out" => 25.0;
+                    Secondary, PhyOut => 25.0;
                 );
             }
             "Potent Destruction Elixir" => {
                 push_passives!(list,
-                    Secondary, "Crit Modifier" => 50.0;
+                    Secondary, CritModifier => 50.0;
                 );
             },
             "Potent Malevolence Elixir" => {
                 push_passives!(list,
-                    Secondary, "Mag Out" => 25.0;
+                    Secondary, MagOut => 25.0;
                 );
             },
             "Unstable Battle Elixir" => {
                 push_passives!(list,
-                    Secondary, "Phy Out" => 28.0;
-                    Secondary, "Critical Chance" => -10.0;
+                    Secondary, PhyOut => 28.0;
+                    Secondary, CritChance => -10.0;
                 );
             },
             "Unstable Malevolence Elixir" => {
                 push_passives!(list,
-                    Secondary, "Mag Out" => 28.0;
-                    Secondary, "Critical Chance" => -10.0;
+                    Secondary, MagOut => 28.0;
+                    Secondary, CritChance => -10.0;
                 );
             },
             _ => {}
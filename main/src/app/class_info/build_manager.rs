@@ -1,12 +1,12 @@
 use yew::prelude::*;
 use wasm_bindgen_futures::spawn_local;
-use crate::app::class_info::class::ClassSettings;
+use backend::build::Build;
 use crate::app::storage::{load_all_builds, save_all_builds};
 
 #[derive(Properties, PartialEq)]
 pub struct BuildManagerProps {
-    pub current_settings: ClassSettings,
-    pub on_load_build: Callback<ClassSettings>,
+    pub current_settings: Build,
+    pub on_load_build: Callback<Build>,
 }
 
 #[function_component(BuildManager)]
@@ -0,0 +1,154 @@
+use yew::prelude::*;
+
+/// Line colours, picked in order for each series.
+pub const COLORS: [&str; 5] = ["#ff7b72", "#79c0ff", "#7ee787", "#d2a8ff", "#ffa657"];
+
+const WIDTH: f32 = 400.0;
+const HEIGHT: f32 = 160.0;
+const PAD: f32 = 24.0;
+
+/// One line on a `LineChart`, points are `(x, y)`.
+#[derive(Clone, PartialEq)]
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(f32, f32)>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LineChartProps {
+    pub series: Vec<Series>,
+    #[prop_or_default]
+    pub x_label: String,
+    /// Holds each value until the next point instead of drawing a slope,
+    /// for things that jump like stacks or mana.
+    #[prop_or_default]
+    pub step: bool,
+}
+
+#[function_component(LineChart)]
+pub fn line_chart(props: &LineChartProps) -> Html {
+    let all_points = props.series.iter().flat_map(|s| s.points.iter());
+    let (mut x_min, mut x_max, mut y_min, mut y_max) = (f32::INFINITY, f32::NEG_INFINITY, f32::INFINITY, f32::NEG_INFINITY);
+    for (x, y) in all_points {
+        x_min = x_min.min(*x);
+        x_max = x_max.max(*x);
+        y_min = y_min.min(*y);
+        y_max = y_max.max(*y);
+    }
+    if !x_min.is_finite() {
+        return html! {};
+    }
+    // Always include 0 on the y axis and avoid a zero-height range
+    y_min = y_min.min(0.0);
+    if y_max <= y_min { y_max = y_min + 1.0; }
+    if x_max <= x_min { x_max = x_min + 1.0; }
+
+    let to_svg = |(x, y): (f32, f32)| -> (f32, f32) {
+        (
+            PAD + (x - x_min) / (x_max - x_min) * (WIDTH - 2.0 * PAD),
+            HEIGHT - PAD - (y - y_min) / (y_max - y_min) * (HEIGHT - 2.0 * PAD),
+        )
+    };
+
+    let polyline = |points: &[(f32, f32)]| -> String {
+        let mut out = Vec::new();
+        for (i, &(x, y)) in points.iter().enumerate() {
+            if props.step && i > 0 {
+                let (sx, sy) = to_svg((x, points[i - 1].1));
+                out.push(format!("{sx:.1},{sy:.1}"));
+            }
+            let (sx, sy) = to_svg((x, y));
+            out.push(format!("{sx:.1},{sy:.1}"));
+        }
+        out.join(" ")
+    };
+
+    html! {
+        <div class="line-chart">
+            <svg viewBox={format!("0 0 {WIDTH} {HEIGHT}")} preserveAspectRatio="none">
+                <line class="axis" x1={PAD.to_string()} y1={(HEIGHT - PAD).to_string()} x2={(WIDTH - PAD).to_string()} y2={(HEIGHT - PAD).to_string()} />
+                <line class="axis" x1={PAD.to_string()} y1={PAD.to_string()} x2={PAD.to_string()} y2={(HEIGHT - PAD).to_string()} />
+                <text x="2" y={(PAD + 4.0).to_string()}>{format!("{y_max:.0}")}</text>
+                <text x="2" y={(HEIGHT - PAD).to_string()}>{format!("{y_min:.0}")}</text>
+                <text x={PAD.to_string()} y={(HEIGHT - 6.0).to_string()}>{format!("{x_min:.0}")}</text>
+                <text x={(WIDTH - PAD).to_string()} y={(HEIGHT - 6.0).to_string()} text-anchor="end">{format!("{x_max:.0} {}", props.x_label)}</text>
+                { for props.series.iter().enumerate().map(|(i, s)| html! {
+                    <polyline fill="none" stroke={COLORS[i % COLORS.len()]} points={polyline(&s.points)} />
+                })}
+            </svg>
+            <div class="line-chart-legend">
+                { for props.series.iter().enumerate().map(|(i, s)| html! {
+                    <span style={format!("color: {};", COLORS[i % COLORS.len()])}>{format!("■ {}", s.label)}</span>
+                })}
+            </div>
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct HeatmapProps {
+    /// Column values, left to right
+    pub xs: Vec<f32>,
+    /// Row values, bottom to top
+    pub ys: Vec<f32>,
+    /// One row per `ys` entry, one value per `xs` entry
+    pub values: Vec<Vec<f32>>,
+    #[prop_or_default]
+    pub x_label: String,
+    #[prop_or_default]
+    pub y_label: String,
+}
+
+/// Blue for the lowest value up to red for the highest.
+fn heat_color(t: f32) -> String {
+    let t = t.clamp(0.0, 1.0);
+    let lerp = |a: f32, b: f32| (a + (b - a) * t).round() as u8;
+    // #1f6feb to #ff7b72
+    format!("rgb({}, {}, {})", lerp(31.0, 255.0), lerp(111.0, 123.0), lerp(235.0, 114.0))
+}
+
+#[function_component(Heatmap)]
+pub fn heatmap(props: &HeatmapProps) -> Html {
+    let (cols, rows) = (props.xs.len(), props.ys.len());
+    let all_values = props.values.iter().flatten().filter(|v| v.is_finite());
+    let (mut v_min, mut v_max) = (f32::INFINITY, f32::NEG_INFINITY);
+    for v in all_values {
+        v_min = v_min.min(*v);
+        v_max = v_max.max(*v);
+    }
+    if cols == 0 || rows == 0 || !v_min.is_finite() {
+        return html! {};
+    }
+    if v_max <= v_min { v_max = v_min + 1.0; }
+
+    let cell_w = (WIDTH - 2.0 * PAD) / cols as f32;
+    let cell_h = (HEIGHT - 2.0 * PAD) / rows as f32;
+
+    html! {
+        <div class="line-chart">
+            <svg viewBox={format!("0 0 {WIDTH} {HEIGHT}")} preserveAspectRatio="none">
+                { for props.values.iter().enumerate().flat_map(|(row, line)| {
+                    line.iter().enumerate().map(move |(col, v)| {
+                        let x = PAD + col as f32 * cell_w;
+                        let y = HEIGHT - PAD - (row + 1) as f32 * cell_h;
+                        html! {
+                            <rect x={x.to_string()} y={y.to_string()} width={cell_w.to_string()} height={cell_h.to_string()}
+                                fill={heat_color((v - v_min) / (v_max - v_min))}>
+                                <title>{format!("{} {:.2}, {} {:.2}: {v:.1}", props.x_label, props.xs[col], props.y_label, props.ys[row])}</title>
+                            </rect>
+                        }
+                    })
+                })}
+                <text x="2" y={(PAD + 4.0).to_string()}>{format!("{:.1}", props.ys[rows - 1])}</text>
+                <text x="2" y={(HEIGHT - PAD).to_string()}>{format!("{:.1}", props.ys[0])}</text>
+                <text x={PAD.to_string()} y={(HEIGHT - 6.0).to_string()}>{format!("{:.1}", props.xs[0])}</text>
+                <text x={(WIDTH - PAD).to_string()} y={(HEIGHT - 6.0).to_string()} text-anchor="end">{format!("{:.1} {}", props.xs[cols - 1], props.x_label)}</text>
+            </svg>
+            <div class="line-chart-legend">
+                <span style={format!("color: {};", heat_color(0.0))}>{format!("■ {v_min:.1}")}</span>
+                <span style={format!("color: {};", heat_color(1.0))}>{format!("■ {v_max:.1}")}</span>
+                <span>{format!("↑ {}", props.y_label)}</span>
+            </div>
+        </div>
+    }
+}
@@ -1,384 +1,21 @@
 use std::str::FromStr;
 use crate::app::class_info::build_manager::BuildManager;
 use crate::app::class_info::dps::DpsCalculator;
-use backend::{damage::{Skill, Type, Weapon, WeaponBoost}, enemy::{EnemySecondaryStats, EnemySkill}, gear::{ Enhancement, EnhancementPattern, GearSlot, get_stats, Trait}, player::{Class, ClassModel, Player, PrimaryStats, SecondaryStats}};
+use backend::{build::Build, damage::{Skill, WeaponBoost}, enemy::{EnemySecondaryStats, EnemySkill}, gear::Enhancement, passive::CustomPassive, player::ClassModel, preset::EnemyPreset, sim::{FightSettings, RotationAction}};
 use gloo_console::log;
 use yew::prelude::*;
-use crate::app::class_info::{enhancement_picker::EnhancementPicker, passive::{CustomPassive, OperationType, TargetType}};
+use crate::app::class_info::enhancement_picker::EnhancementPicker;
 use crate::app::class_info::stats::StatDisplay;
 use crate::app::class_info::skills::Skills;
 use crate::app::class_info::passive::PassiveManager;
 use crate::app::class_info::buffs::BuffManager;
 use crate::app::class_info::enemy::EnemyPanel;
 use crate::app::class_info::enemy_skill::EnemySkills;
-use serde::{Serialize, Deserialize};
-
-#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
-pub struct ClassSettings {
-    pub name: String,
-    pub level: Player,
-    pub equipment: Equipment,
-    pub weapon: Weapon,
-    pub class: Class,
-    pub primary_stats: PrimaryStats,
-    pub secondary_stats: SecondaryStats,
-    pub passives: Vec<CustomPassive>,
-    pub skills: Vec<(Skill, Vec<CustomPassive>, bool)>,
-    pub enemy: EnemySecondaryStats,
-    pub enemy_skills: Vec<(EnemySkill, Vec<CustomPassive>, bool)>,
-}
-
-#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
-pub struct Equipment {
-    pub helm: Enhancement,
-    pub cape: Enhancement,
-    pub weapon: Enhancement,
-    pub class: Enhancement,
-}
-
-impl Equipment {
-    pub fn total_stats(&self) -> PrimaryStats {
-        let mut total = get_stats(&self.helm, GearSlot::Helm);
-        total.add(&get_stats(&self.cape, GearSlot::Cape));
-        total.add(&get_stats(&self.weapon, GearSlot::Weapon));
-        total.add(&get_stats(&self.class, GearSlot::Armor));
-
-        total
-    }
-}
-
-impl Default for Equipment {
-    fn default() -> Self {
-        Equipment {
-            helm:  Enhancement { level: 100, rarity: 6, pattern: EnhancementPattern::Anima, r#trait: Trait::None },
-            cape: Enhancement { level: 100, rarity: 6, pattern: EnhancementPattern::Forge, r#trait: Trait::None },
-            weapon: Enhancement { level: 100, rarity: 6, pattern: EnhancementPattern::Forge, r#trait: Trait::None },
-            class: Enhancement { level: 100, rarity: 5, pattern: EnhancementPattern::Lucky, r#trait: Trait::None },
-        }
-    }
-}
-
-impl Default for ClassSettings {
-    fn default() -> Self {
-        let player = Player::default();
-        let equipment = Equipment::default();
-        let class = Class::default();
-        let mut primary_stats = class.class_model.level_primary_stat_total(&player);
-        let equip_stats = equipment.total_stats();
-        primary_stats.add(&equip_stats);
-
-        ClassSettings {
-            name: "Archfishy".into(),
-            level: player.clone(),
-            equipment: equipment.clone(),
-            weapon: Weapon { range: 1.0, dps: 85.0, boost: backend::damage::WeaponBoost::Boost51x50 },
-            class: class.clone(),
-            primary_stats: primary_stats.clone(),
-            secondary_stats: class.class_model.secondary_stats_convert(&player, &primary_stats),
-            passives: vec![],
-            skills: vec![(Skill::default(), vec![], false); 5],
-            enemy: EnemySecondaryStats::new(),
-            enemy_skills: vec![(EnemySkill::default(), vec![], false); 1],
-        }
-    }
-
-}
-
-pub fn calculate_primary_changes(primary_stats: &mut PrimaryStats, passive: &CustomPassive) -> PrimaryStats {
-    if passive.target_type == TargetType::Primary {
-        
-        match passive.stat_name.as_str() {
-            "Strength" => {
-                match passive.operation_type {
-                    OperationType::Additive => primary_stats.strength += passive.value as i32,
-                    OperationType::Multiplicative => {
-                        let stat = primary_stats.strength;
-                        primary_stats.strength = (stat as f32 * passive.value) as i32;
-                    }
-                }
-            },
-            "Dexterity" => {
-                match passive.operation_type {
-                    OperationType::Additive => primary_stats.dexterity += passive.value as i32,
-                    OperationType::Multiplicative => {
-                        let stat = primary_stats.dexterity;
-                        primary_stats.dexterity = (stat as f32 * passive.value) as i32;
-                    }
-                }
-            },
-            "Wisdom" => {
-                match passive.operation_type {
-                    OperationType::Additive => primary_stats.wisdom += passive.value as i32,
-                    OperationType::Multiplicative => primary_stats.wisdom *= {
-                        let stat = primary_stats.wisdom as f32;
-                        (stat * passive.value).round() as i32
-                    }
-                }
-            },
-            "Intellect" => {
-                match passive.operation_type {
-                    OperationType::Additive => primary_stats.intellect += passive.value as i32,
-                    OperationType::Multiplicative => {
-                        let stat = primary_stats.intellect as f32;
-
-                        primary_stats.intellect = (stat * passive.value).round() as i32;
-                    },
-                }
-            },
-            "Endurance" => {
-                match passive.operation_type {
-                    OperationType::Additive => primary_stats.endurance += passive.value as i32,
-                    OperationType::Multiplicative => {
-                        let stat = primary_stats.endurance as f32;
-
-                        primary_stats.endurance = (stat * passive.value).round() as i32;
-                    }
-                }
-            },
-            "Luck" => {
-                match passive.operation_type {
-                    OperationType::Additive => primary_stats.luck += passive.value as i32,
-                    OperationType::Multiplicative => {
-                        let stat = primary_stats.luck as f32;
-
-                        primary_stats.luck = (stat * passive.value).round() as i32;
-                    }
-                }
-            },
-            _ => (),
-        }
-    }
-    primary_stats.clone()
-}
-
-pub fn calculate_secondary_changes(secondary_stats: &mut SecondaryStats, passive: &CustomPassive) -> SecondaryStats {
-    if passive.target_type == TargetType::Secondary {
-        
-        match passive.stat_name.as_str() {
-            "All Out" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.all_out += passive.value,
-                    OperationType::Multiplicative => secondary_stats.all_out *= passive.value,
-                }
-            },
-            "All In" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.all_in -= passive.value,
-                    OperationType::Multiplicative => secondary_stats.all_in *= passive.value,
-                }
-            },
-            "Phy Out" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.phy_out += passive.value,
-                    OperationType::Multiplicative => secondary_stats.phy_out *= passive.value,
-                }
-            },
-            "Phy In" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.phy_in -= passive.value,
-                    OperationType::Multiplicative => secondary_stats.phy_in *= passive.value,
-                }
-            },
-            "Mag Out" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.mag_out += passive.value,
-                    OperationType::Multiplicative => secondary_stats.mag_out *= passive.value,
-                }
-            },
-            "Mag In" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.mag_in -= passive.value,
-                    OperationType::Multiplicative => secondary_stats.mag_in *= passive.value,
-                }
-            },
-            "Heal Out" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.heal_out += passive.value,
-                    OperationType::Multiplicative => secondary_stats.heal_out *= passive.value,
-                }
-            },
-            "Heal In" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.heal_in -= passive.value,
-                    OperationType::Multiplicative => secondary_stats.heal_in *= passive.value,
-                }
-            },
-            "DoT Out" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.dot_out += passive.value,
-                    OperationType::Multiplicative => secondary_stats.dot_out *= passive.value,
-                }
-            },
-            "DoT In" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.dot_in -= passive.value,
-                    OperationType::Multiplicative => secondary_stats.dot_in *= passive.value,
-                }
-            },
-            "Mana Consumption" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.mana_consumption += passive.value,
-                    OperationType::Multiplicative => secondary_stats.mana_consumption *= passive.value,
-                }
-            },
-            "Attack Power" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.attack_power += passive.value,
-                    OperationType::Multiplicative => secondary_stats.attack_power *= passive.value,
-                }
-            },
-            "Spell Power" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.spell_power += passive.value,
-                    OperationType::Multiplicative => secondary_stats.spell_power *= passive.value,
-                }
-            },
-            "Hit Chance" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.hit_chance += passive.value,
-                    OperationType::Multiplicative => secondary_stats.hit_chance *= passive.value,
-                }
-            },
-            "Haste" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.haste += passive.value,
-                    OperationType::Multiplicative => secondary_stats.haste *= passive.value,
-                }
-            },
-            "Dodge Chance" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.dodge += passive.value,
-                    OperationType::Multiplicative => secondary_stats.dodge *= passive.value,
-                }
-            },
-            "Crit Chance" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.crit_chance += passive.value,
-                    OperationType::Multiplicative => secondary_stats.crit_chance *= passive.value,
-                }
-            },
-            "Crit Modifier" => {
-                match passive.operation_type {
-                    OperationType::Additive => secondary_stats.crit_mod += passive.value,
-                    OperationType::Multiplicative => secondary_stats.crit_mod *= passive.value,
-                }
-            },
-
-            _ => (),
-
-        }
-    }
-    secondary_stats.clone()
-}
-
-/// Applies a single enemy-targeted passive (debuff) to an EnemySecondaryStats snapshot.
-/// Additive debuffs ADD to the enemy stat (e.g. +25 Phy In → enemy takes 25% more physical).
-/// Multiplicative debuffs MULTIPLY the enemy stat.
-pub fn calculate_enemy_changes(enemy_stats: &mut EnemySecondaryStats, passive: &CustomPassive) -> EnemySecondaryStats {
-    if passive.target_type != TargetType::Enemy {
-        return enemy_stats.clone();
-    }
-    match passive.stat_name.as_str() {
-        "All In" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.all_in += passive.value,
-            OperationType::Multiplicative => enemy_stats.all_in *= passive.value,
-        },
-        "Phy In" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.phy_in += passive.value,
-            OperationType::Multiplicative => enemy_stats.phy_in *= passive.value,
-        },
-        "Mag In" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.mag_in += passive.value,
-            OperationType::Multiplicative => enemy_stats.mag_in *= passive.value,
-        },
-        "DoT In" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.dot_in += passive.value,
-            OperationType::Multiplicative => enemy_stats.dot_in *= passive.value,
-        },
-        "Heal In" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.heal_in += passive.value,
-            OperationType::Multiplicative => enemy_stats.heal_in *= passive.value,
-        },
-        "All Out" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.all_out += passive.value,
-            OperationType::Multiplicative => enemy_stats.all_out *= passive.value,
-        },
-        "Phy Out" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.phy_out += passive.value,
-            OperationType::Multiplicative => enemy_stats.phy_out *= passive.value,
-        },
-        "Mag Out" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.mag_out += passive.value,
-            OperationType::Multiplicative => enemy_stats.mag_out *= passive.value,
-        },
-        "DoT Out" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.dot_out += passive.value,
-            OperationType::Multiplicative => enemy_stats.dot_out *= passive.value,
-        },
-        "Heal Out" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.heal_out += passive.value,
-            OperationType::Multiplicative => enemy_stats.heal_out *= passive.value,
-        },
-        "Crit Chance" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.crit_chance += passive.value,
-            OperationType::Multiplicative => enemy_stats.crit_chance *= passive.value,
-        },
-        "Crit Modifier" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.crit_mod += passive.value,
-            OperationType::Multiplicative => enemy_stats.crit_mod *= passive.value,
-        },
-        "Haste" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.haste += passive.value,
-            OperationType::Multiplicative => enemy_stats.haste *= passive.value,
-        },
-        "Dodge" => match passive.operation_type {
-            OperationType::Additive => enemy_stats.dodge += passive.value,
-            OperationType::Multiplicative => enemy_stats.dodge *= passive.value,
-        },
-        _ => ()
-    }
-    enemy_stats.clone()
-}
-
-pub fn enemy_incoming_modifier(damage_type: &Type, enemy: &EnemySecondaryStats) -> f32 {
-    let all_in = enemy.all_in / 100.0;
-    match damage_type {
-        Type::Physical      => all_in * (enemy.phy_in / 100.0),
-        Type::Magical       => all_in * (enemy.mag_in / 100.0),
-        Type::DamageOverTime => all_in * (enemy.dot_in / 100.0),
-        Type::TrueDamage    => 1.0,
-    }
-}
-
-
-
-
-impl ClassSettings {
-    pub fn refresh_stats(&mut self) {
-        let mut primary_stats = self.class.class_model.level_primary_stat_total(&self.level);
-        primary_stats.add(&self.equipment.total_stats());
-
-        // Always start from the fresh base; the loop applies passives on top
-        self.primary_stats = primary_stats.clone();
-        for passive in &self.passives {
-            self.primary_stats = calculate_primary_changes(&mut primary_stats, passive);
-        }
-
-        let mut secondary_stats = self.class.class_model.secondary_stats_convert(&self.level, &self.primary_stats);
-
-        // Always start from the fresh base; the loop applies passives on top
-        self.secondary_stats = secondary_stats.clone();
-        for passive in &self.passives {
-            self.secondary_stats = calculate_secondary_changes(&mut secondary_stats, passive);
-        }
-
-    }
-}
 
 #[allow(clippy::redundant_closure)]
 #[function_component(PlayerSettings)]
 pub fn player_settings() -> Html {
-    let settings = use_state(|| ClassSettings::default());
+    let settings = use_state(|| Build::default());
 
     web_sys::console::log_1(&format!("Current Stat in Rust: {:?}", settings.equipment.helm).into());
     let on_level_input = {
@@ -484,6 +121,28 @@ pub fn player_settings() -> Html {
         })
     };
 
+    let on_load_enemy_preset = {
+        let settings = settings.clone();
+        Callback::from(move |preset: EnemyPreset| {
+            let mut new_s = (*settings).clone();
+            preset.apply(&mut new_s);
+            settings.set(new_s);
+        })
+    };
+
+    // Optimizer results, unlike loading a build this keeps the rest of the UI as it is
+    let on_apply_build = {
+        let settings = settings.clone();
+        Callback::from(move |build: Build| settings.set(build))
+    };
+
+    // The fight and rotation set up in the DPS panel, so stat weights describe the same fight
+    let dps_fight = use_state(|| (FightSettings::default(), Vec::<RotationAction>::new()));
+    let on_fight_change = {
+        let dps_fight = dps_fight.clone();
+        Callback::from(move |fight: (FightSettings, Vec<RotationAction>)| dps_fight.set(fight))
+    };
+
     let on_update_enemy = {
         let settings = settings.clone();
         Callback::from(move |new_enemy: EnemySecondaryStats| {
@@ -498,7 +157,7 @@ pub fn player_settings() -> Html {
     let on_load_build = {
         let settings = settings.clone();
         let load_count = load_count.clone();
-        Callback::from(move |mut loaded_settings: ClassSettings| {
+        Callback::from(move |mut loaded_settings: Build| {
 
             loaded_settings.refresh_stats();
             log!(format!("{:?}", loaded_settings));
@@ -658,7 +317,7 @@ pub fn player_settings() -> Html {
                             }
                         </div>
                     </div>
-                <StatDisplay settings={(*settings).clone()} />
+                <StatDisplay settings={(*settings).clone()} fight={dps_fight.0.clone()} rotation={dps_fight.1.clone()} />
                 <BuffManager settings={(*settings).clone()} on_update_passives={&on_add_passive} />
                 <PassiveManager settings={(*settings).clone()} on_update_passives={&on_add_passive} />
                 <hr />
@@ -673,12 +332,12 @@ pub fn player_settings() -> Html {
             </div>
             <div class="panel-right">
                 <Skills settings={(*settings).clone()} on_update_skills={on_update_skills}/>
-                <EnemyPanel settings={(*settings).clone()} on_update_enemy={on_update_enemy} />
+                <EnemyPanel settings={(*settings).clone()} on_update_enemy={on_update_enemy} on_load_preset={on_load_enemy_preset} />
                 <EnemySkills 
                     enemy_skills={settings.enemy_skills.clone()}
                     on_update_skills={on_update_enemy_skills}
                 />
-                <DpsCalculator settings={(*settings).clone()} />
+                <DpsCalculator settings={(*settings).clone()} on_apply_build={on_apply_build} on_fight_change={on_fight_change} />
             </div>
 
         </div>
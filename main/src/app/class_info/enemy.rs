@@ -1,12 +1,16 @@
 use yew::prelude::*;
-use backend::enemy::EnemySecondaryStats;
-use crate::app::class_info::class::ClassSettings;
+use backend::enemy::{EnemySecondaryStats, PhaseTrigger};
+use backend::preset::{EnemyPreset, enemy_presets};
+use backend::stat::StatKey;
+use backend::build::Build;
 
 
 #[derive(Properties, PartialEq)]
 pub struct EnemyProps {
-    pub settings: ClassSettings,
+    pub settings: Build,
     pub on_update_enemy: Callback<EnemySecondaryStats>,
+    /// Fired with the picked preset, which replaces the enemy's stats and skills.
+    pub on_load_preset: Callback<EnemyPreset>,
 }
 
 /// Generates an `oninput` callback that updates one f32 field on EnemySecondaryStats.
@@ -52,6 +56,30 @@ pub fn enemy_panel(props: &EnemyProps) -> Html {
     let enemy = props.settings.enemy.clone();
     let on_update = props.on_update_enemy.clone();
 
+    // --- Preset picker ---
+    let presets = use_memo((), |_| enemy_presets());
+    let query = use_state(String::new);
+    let matching: Vec<EnemyPreset> = presets.iter().filter(|p| p.matches(&query)).cloned().collect();
+
+    let on_search = {
+        let query = query.clone();
+        Callback::from(move |e: InputEvent| {
+            query.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value());
+        })
+    };
+
+    let on_pick = {
+        let presets = presets.clone();
+        let on_load = props.on_load_preset.clone();
+        Callback::from(move |e: Event| {
+            let input = e.target_unchecked_into::<web_sys::HtmlInputElement>();
+            if let Some(preset) = presets.iter().find(|p| p.name == input.value()) {
+                on_load.emit(preset.clone());
+            }
+            input.set_value(""); // Reset selection to placeholder
+        })
+    };
+
     let cb_all_in      = f32_cb!(enemy, on_update, all_in);
     let cb_phy_in      = f32_cb!(enemy, on_update, phy_in);
     let cb_mag_in      = f32_cb!(enemy, on_update, mag_in);
@@ -75,10 +103,14 @@ pub fn enemy_panel(props: &EnemyProps) -> Html {
     let cb_mp = i32_cb!(enemy, on_update, mp);
 
     macro_rules! stat_row_f32 {
-        ($label:expr, $val:expr, $step:expr, $cb:expr) => {
+        ($label:expr, $val:expr, $step:expr, $cb:expr, $key:expr) => {
             html! {
                 <div class="stat-row">
                     <span class="label">{$label}</span>
+                    // The value the fight actually uses, when the cap changes it
+                    { if $key.clamp($val) != $val {
+                        html! { <span class="capped-value">{format!("→ {:.2}", $key.clamp($val))}</span> }
+                    } else { html! {} }}
                     <input
                         type="number"
                         step={$step}
@@ -113,31 +145,69 @@ pub fn enemy_panel(props: &EnemyProps) -> Html {
     html! {
         <div class="enemy-panel panel-right-section" style="margin-bottom: 20px;">
             <h3>{"Enemy Configuration"}</h3>
+            <div class="build-io enemy-preset-picker">
+                <input
+                    type="text"
+                    class="table-input"
+                    placeholder="Search enemies..."
+                    value={(*query).clone()}
+                    oninput={on_search}
+                />
+                <select class="io-btn" style="cursor: pointer; text-align: center;" onchange={on_pick}>
+                    <option value="" disabled=true selected=true>
+                        { if matching.is_empty() { "No matching enemy".to_string() } else { format!("Load Preset ({})", matching.len()) } }
+                    </option>
+                    { for matching.iter().map(|p| html! {
+                        <option value={p.name.clone()} title={p.description.clone()}>{&p.name}</option>
+                    })}
+                </select>
+            </div>
             <div class="stats-screen">
                 <div class="stat-block">
                     <h4>{"Secondary"}</h4>
 
-                    { stat_row_f32!("All In (%)",         enemy.all_in,          "0.1", cb_all_in)      }
-                    { stat_row_f32!("Phy In (%)",         enemy.phy_in,          "0.1", cb_phy_in)      }
-                    { stat_row_f32!("Mag In (%)",         enemy.mag_in,          "0.1", cb_mag_in)      }
-                    { stat_row_f32!("DoT In (%)",         enemy.dot_in,          "0.1", cb_dot_in)      }
-                    { stat_row_f32!("Heal In (%)",        enemy.heal_in,         "0.1", cb_heal_in)     }
-                    { stat_row_f32!("All Out (%)",        enemy.all_out,         "0.1", cb_all_out)     }
-                    { stat_row_f32!("Phy Out (%)",        enemy.phy_out,         "0.1", cb_phy_out)     }
-                    { stat_row_f32!("Mag Out (%)",        enemy.mag_out,         "0.1", cb_mag_out)     }
-                    { stat_row_f32!("DoT Out (%)",        enemy.dot_out,         "0.1", cb_dot_out)     }
-                    { stat_row_f32!("Heal Out (%)",       enemy.heal_out,        "0.1", cb_heal_out)    }
-                    { stat_row_f32!("Crit Chance (%)",    enemy.crit_chance,     "0.1", cb_crit_chance) }
-                    { stat_row_f32!("Crit Modifier (%)",  enemy.crit_mod,        "0.1", cb_crit_mod)    }
-                    { stat_row_f32!("Haste (%)",          enemy.haste,           "0.1", cb_haste)       }
-                    { stat_row_f32!("Dodge (%)",          enemy.dodge,           "0.1", cb_dodge)       }
-                    { stat_row_f32!("Hit Chance (%)",     enemy.hit_chance,      "0.1", cb_hit_chance)  }
-                    { stat_row_f32!("Attack Power",       enemy.attack_power,    "1",   cb_ap)          }
-                    { stat_row_f32!("Spell Power",        enemy.spell_power,     "1",   cb_sp)          }
-                    { stat_row_f32!("Mana Con (%)",       enemy.mana_consumption,"0.1", cb_mana_con)    }
+                    { stat_row_f32!("All In (%)",         enemy.all_in,          "0.1", cb_all_in,      StatKey::AllIn) }
+                    { stat_row_f32!("Phy In (%)",         enemy.phy_in,          "0.1", cb_phy_in,      StatKey::PhyIn) }
+                    { stat_row_f32!("Mag In (%)",         enemy.mag_in,          "0.1", cb_mag_in,      StatKey::MagIn) }
+                    { stat_row_f32!("DoT In (%)",         enemy.dot_in,          "0.1", cb_dot_in,      StatKey::DotIn) }
+                    { stat_row_f32!("Heal In (%)",        enemy.heal_in,         "0.1", cb_heal_in,     StatKey::HealIn) }
+                    { stat_row_f32!("All Out (%)",        enemy.all_out,         "0.1", cb_all_out,     StatKey::AllOut) }
+                    { stat_row_f32!("Phy Out (%)",        enemy.phy_out,         "0.1", cb_phy_out,     StatKey::PhyOut) }
+                    { stat_row_f32!("Mag Out (%)",        enemy.mag_out,         "0.1", cb_mag_out,     StatKey::MagOut) }
+                    { stat_row_f32!("DoT Out (%)",        enemy.dot_out,         "0.1", cb_dot_out,     StatKey::DotOut) }
+                    { stat_row_f32!("Heal Out (%)",       enemy.heal_out,        "0.1", cb_heal_out,    StatKey::HealOut) }
+                    { stat_row_f32!("Crit Chance (%)",    enemy.crit_chance,     "0.1", cb_crit_chance, StatKey::CritChance) }
+                    { stat_row_f32!("Crit Modifier (%)",  enemy.crit_mod,        "0.1", cb_crit_mod,    StatKey::CritModifier) }
+                    { stat_row_f32!("Haste (%)",          enemy.haste,           "0.1", cb_haste,       StatKey::Haste) }
+                    { stat_row_f32!("Dodge (%)",          enemy.dodge,           "0.1", cb_dodge,       StatKey::Dodge) }
+                    { stat_row_f32!("Hit Chance (%)",     enemy.hit_chance,      "0.1", cb_hit_chance,  StatKey::HitChance) }
+                    { stat_row_f32!("Attack Power",       enemy.attack_power,    "1",   cb_ap,          StatKey::AttackPower) }
+                    { stat_row_f32!("Spell Power",        enemy.spell_power,     "1",   cb_sp,          StatKey::SpellPower) }
+                    { stat_row_f32!("Mana Con (%)",       enemy.mana_consumption,"0.1", cb_mana_con,    StatKey::ManaConsumption) }
                     { stat_row_i32!("Max HP",             enemy.hp,              cb_hp)                 }
                     { stat_row_i32!("Max MP",             enemy.mp,              cb_mp)                 }
                 </div>
+                // Phases come with presets, the opening phase is everything above
+                { if !props.settings.enemy_phases.is_empty() {
+                    html! {
+                        <div class="stat-block">
+                            <h4>{"Phases"}</h4>
+                            { for props.settings.enemy_phases.iter().map(|phase| {
+                                let trigger = match phase.trigger {
+                                    PhaseTrigger::HpBelow(hp) => format!("HP ≤ {hp:.0}%"),
+                                    PhaseTrigger::Time(t) => format!("at {t:.0}s"),
+                                };
+                                let invulnerable = if phase.invulnerable > 0.0 { format!(", invulnerable {:.1}s", phase.invulnerable) } else { String::new() };
+                                html! {
+                                    <div class="stat-row">
+                                        <span class="label">{&phase.name}</span>
+                                        <span class="value">{format!("{trigger}, {} skills{invulnerable}", phase.skills.len())}</span>
+                                    </div>
+                                }
+                            })}
+                        </div>
+                    }
+                } else { html! {} }}
             </div>
         </div>
     }
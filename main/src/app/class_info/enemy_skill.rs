@@ -1,6 +1,8 @@
+use std::str::FromStr;
 use yew::prelude::*;
-use crate::app::class_info::passive::{CustomPassive, TargetType, OperationType};
-use backend::enemy::EnemySkill; 
+use backend::{passive::{CustomPassive, TargetType, OperationType}, stat::StatKey};
+use backend::damage::{ForceResult, Target, Type};
+use backend::enemy::{EnemyScaling, EnemySkill};
 
 #[derive(Properties, PartialEq)]
 pub struct EnemySkillProps {
@@ -13,20 +15,6 @@ pub fn enemy_skills(props: &EnemySkillProps) -> Html {
     let on_update_parent = props.on_update_skills.clone();
     let skills_list = &props.enemy_skills;
 
-    // Stat dropdown options
-    let primary_options = vec!["Strength", "Intellect", "Endurance", "Dexterity", "Wisdom", "Luck"];
-    let secondary_options = vec![
-        "Haste", "Crit Chance", "Hit Chance", "Dodge Chance",
-        "All Out", "Phy Out", "Mag Out", "Heal Out",
-        "All In", "Phy In", "Mag In", "Heal In",
-        "DoT In", "DoT Out", "Mana Consumption",
-        "Attack Power", "Spell Power", "Crit Modifier"
-    ];
-    let enemy_options = vec![
-        "All In", "Phy In", "Mag In", "DoT In", "Heal In",
-        "All Out", "Phy Out", "Mag Out", "DoT Out", "Heal Out",
-        "Crit Chance", "Crit Modifier", "Haste", "Dodge",
-    ];
 
     let update_skill_at = {
         let skills_list = skills_list.clone();
@@ -49,6 +37,10 @@ pub fn enemy_skills(props: &EnemySkillProps) -> Html {
                         <th>{"#"}</th>
                         <th>{"Base Damage"}</th>
                         <th>{"CD (ms)"}</th>
+                        <th>{"Type"}</th>
+                        <th>{"Scaling"}</th>
+                        <th>{"Target"}</th>
+                        <th>{"Force"}</th>
                         <th>{"Can Crit?"}</th>
                         <th>{"Actions"}</th>
                     </tr>
@@ -97,6 +89,93 @@ pub fn enemy_skills(props: &EnemySkillProps) -> Html {
                                         />
                                     </td>
 
+                                    // Damage Type Select
+                                    <td>
+                                        <select onchange={
+                                            let s = skill.clone();
+                                            let p = passives.clone();
+                                            let up = up_cb.clone();
+                                            Callback::from(move |e: Event| {
+                                                let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                                if let Ok(t) = Type::from_str(&val) {
+                                                    let mut s = s.clone();
+                                                    s.damage_type = t;
+                                                    up.emit((i, s, p.clone(), is_crit));
+                                                }
+                                            })
+                                        }>
+                                            <option value="Physical" selected={skill.damage_type == Type::Physical}>{"Physical"}</option>
+                                            <option value="Magical" selected={skill.damage_type == Type::Magical}>{"Magical"}</option>
+                                            <option value="TrueDamage" selected={skill.damage_type == Type::TrueDamage}>{"True"}</option>
+                                            <option value="DamageOverTime" selected={skill.damage_type == Type::DamageOverTime}>{"DoT"}</option>
+                                            <option value="Heal" selected={skill.damage_type == Type::Heal}>{"Heal"}</option>
+                                        </select>
+                                    </td>
+
+                                    // Scaling Select
+                                    <td>
+                                        <select onchange={
+                                            let s = skill.clone();
+                                            let p = passives.clone();
+                                            let up = up_cb.clone();
+                                            Callback::from(move |e: Event| {
+                                                let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                                if let Ok(scaling) = EnemyScaling::from_str(&val) {
+                                                    let mut s = s.clone();
+                                                    s.scaling = scaling;
+                                                    up.emit((i, s, p.clone(), is_crit));
+                                                }
+                                            })
+                                        }>
+                                            <option value="Flat" selected={skill.scaling == EnemyScaling::Flat}>{"Flat"}</option>
+                                            <option value="AttackPower" selected={skill.scaling == EnemyScaling::AttackPower}>{"+ AP"}</option>
+                                            <option value="SpellPower" selected={skill.scaling == EnemyScaling::SpellPower}>{"+ SP"}</option>
+                                            <option value="PlayerMaxHp" selected={skill.scaling == EnemyScaling::PlayerMaxHp}>{"% Player HP"}</option>
+                                        </select>
+                                    </td>
+
+                                    // Target Select, from the enemy's side
+                                    <td>
+                                        <select onchange={
+                                            let s = skill.clone();
+                                            let p = passives.clone();
+                                            let up = up_cb.clone();
+                                            Callback::from(move |e: Event| {
+                                                let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                                if let Ok(t) = Target::from_str(&val) {
+                                                    let mut s = s.clone();
+                                                    s.target = t;
+                                                    up.emit((i, s, p.clone(), is_crit));
+                                                }
+                                            })
+                                        }>
+                                            <option value="Enemy" selected={skill.target == Target::Enemy}>{"Player"}</option>
+                                            <option value="Self" selected={skill.target == Target::Yourself}>{"Self"}</option>
+                                        </select>
+                                    </td>
+
+                                    // Forced Result Select
+                                    <td>
+                                        <select onchange={
+                                            let s = skill.clone();
+                                            let p = passives.clone();
+                                            let up = up_cb.clone();
+                                            Callback::from(move |e: Event| {
+                                                let mut s = s.clone();
+                                                s.force_result = match e.target_unchecked_into::<web_sys::HtmlInputElement>().value().as_str() {
+                                                    "Hit" => Some(ForceResult::Hit),
+                                                    "Crit" => Some(ForceResult::Crit),
+                                                    _ => None,
+                                                };
+                                                up.emit((i, s, p.clone(), is_crit));
+                                            })
+                                        }>
+                                            <option value="None" selected={skill.force_result.is_none()}>{"-"}</option>
+                                            <option value="Hit" selected={skill.force_result == Some(ForceResult::Hit)}>{"Always Hit"}</option>
+                                            <option value="Crit" selected={skill.force_result == Some(ForceResult::Crit)}>{"Always Crit"}</option>
+                                        </select>
+                                    </td>
+
                                     // Crit Toggle
                                     <td>
                                         <input type="checkbox" checked={is_crit}
@@ -139,11 +218,7 @@ pub fn enemy_skills(props: &EnemySkillProps) -> Html {
                                 // Passives / Buffs Sub-rows
                                 { for passives.iter().enumerate().map(|(p_idx, current_passive)| {
                                     let current_passive = current_passive.clone();
-                                    let stat_options = match current_passive.target_type {
-                                        TargetType::Primary   => &primary_options,
-                                        TargetType::Secondary => &secondary_options,
-                                        TargetType::Enemy     => &enemy_options,
-                                    };
+                                    let stat_options = StatKey::options(&current_passive.target_type);
                                     
                                     // Semantic note: "Enemy" target from the enemy's perspective means buffing itself.
                                     // "Primary/Secondary" target from the enemy's perspective means debuffing the player.
@@ -151,7 +226,7 @@ pub fn enemy_skills(props: &EnemySkillProps) -> Html {
 
                                     html! {
                                         <tr key={format!("enemy-skill-{}-buff-{}", i, p_idx)} class="skill-buff-row">
-                                            <td colspan="5">
+                                            <td colspan="9">
                                                 <div class="buff-editor">
                                                     <span class="buff-prefix" style={if is_player_debuff { "color: #f85149;" } else { "" }}>
                                                         { if is_player_debuff { format!("↳ Player Debuff #{}", p_idx + 1) } else { format!("↳ Enemy Buff #{}", p_idx + 1) } }
@@ -169,15 +244,15 @@ pub fn enemy_skills(props: &EnemySkillProps) -> Html {
                                                                 match val.as_str() {
                                                                     "Secondary" => {
                                                                         p.target_type = TargetType::Secondary;
-                                                                        p.stat_name = "Haste".to_string();
+                                                                        p.stat_name = StatKey::Haste;
                                                                     },
                                                                     "Enemy" => {
                                                                         p.target_type = TargetType::Enemy;
-                                                                        p.stat_name = "All In".to_string();
+                                                                        p.stat_name = StatKey::AllIn;
                                                                     },
                                                                     _ => {
                                                                         p.target_type = TargetType::Primary;
-                                                                        p.stat_name = "Strength".to_string();
+                                                                        p.stat_name = StatKey::Strength;
                                                                     }
                                                                 }
                                                                 up.emit((i, s.clone(), p_list.clone(), is_crit));
@@ -197,13 +272,13 @@ pub fn enemy_skills(props: &EnemySkillProps) -> Html {
                                                         Callback::from(move |e: Event| {
                                                             let mut p_list = p_list.clone();
                                                             if let Some(p) = p_list.get_mut(p_idx) {
-                                                                p.stat_name = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                                                if let Ok(key) = StatKey::from_str(&e.target_unchecked_into::<web_sys::HtmlInputElement>().value()) { p.stat_name = key; }
                                                                 up.emit((i, s.clone(), p_list.clone(), is_crit));
                                                             }
                                                         })
                                                     }>
                                                         { for stat_options.iter().map(|opt| html! {
-                                                            <option value={*opt} selected={&current_passive.stat_name == opt}>{opt}</option>
+                                                            <option value={opt.to_string()} selected={current_passive.stat_name == *opt}>{opt.to_string()}</option>
                                                         })}
                                                     </select>
 
@@ -218,6 +293,7 @@ pub fn enemy_skills(props: &EnemySkillProps) -> Html {
                                                                 let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
                                                                 p.operation_type = match val.as_str() {
                                                                     "Multiplicative" => OperationType::Multiplicative,
+                                                                    "Final" => OperationType::Final,
                                                                     _ => OperationType::Additive,
                                                                 };
                                                                 up.emit((i, s.clone(), p_list.clone(), is_crit));
@@ -226,6 +302,7 @@ pub fn enemy_skills(props: &EnemySkillProps) -> Html {
                                                     }>
                                                         <option value="Additive" selected={current_passive.operation_type == OperationType::Additive}>{"Add"}</option>
                                                         <option value="Multiplicative" selected={current_passive.operation_type == OperationType::Multiplicative}>{"Mult"}</option>
+                                                        <option value="Final" selected={current_passive.operation_type == OperationType::Final}>{"Final"}</option>
                                                     </select>
 
                                                     // Value Input
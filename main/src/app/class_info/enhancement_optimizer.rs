@@ -0,0 +1,126 @@
+use yew::prelude::*;
+use backend::build::{Build, Equipment};
+use backend::gear::GearSlot;
+use backend::optimizer::{EnhancementSearch, LoadoutMetric, RankedLoadout, optimize_enhancements};
+use backend::sim::{FightSettings, RotationAction};
+
+#[derive(Properties, PartialEq)]
+pub struct EnhancementOptimizerProps {
+    pub settings: Build,
+    pub fight: FightSettings,
+    pub rotation: Vec<RotationAction>,
+    /// Fired with the build wearing the picked gear
+    pub on_apply: Callback<Build>,
+}
+
+fn slot_name(slot: GearSlot) -> &'static str {
+    match slot {
+        GearSlot::Helm => "Helm",
+        GearSlot::Cape => "Cape",
+        GearSlot::Weapon => "Weapon",
+        GearSlot::Armor => "Class",
+    }
+}
+
+/// `+1.5` / `-3.0`, with the sign always shown
+fn signed(value: f32) -> String {
+    if value.is_finite() { format!("{value:+.1}") } else { "∞".to_string() }
+}
+
+#[function_component(EnhancementOptimizer)]
+pub fn enhancement_optimizer(props: &EnhancementOptimizerProps) -> Html {
+    let metric = use_state(LoadoutMetric::default);
+    let per_slot = use_state(|| 3_usize);
+    // Kept with the metric it ran for, the select can change afterwards
+    let results = use_state(|| (LoadoutMetric::default(), Vec::<RankedLoadout>::new()));
+
+    let on_search = {
+        let settings = props.settings.clone();
+        let fight = props.fight.clone();
+        let rotation = props.rotation.clone();
+        let metric = metric.clone();
+        let per_slot = per_slot.clone();
+        let results = results.clone();
+        Callback::from(move |_| {
+            let search = EnhancementSearch { metric: *metric, per_slot: *per_slot, ..Default::default() };
+            results.set((*metric, optimize_enhancements(&settings, &fight, &rotation, &search)));
+        })
+    };
+
+    html! {
+        <div class="stat-block enhancement-optimizer">
+            <h4>{"Enhancement Optimizer"}</h4>
+            <div class="input-field">
+                <label>{"Optimize For: "}</label>
+                <select onchange={
+                    let metric = metric.clone();
+                    Callback::from(move |e: Event| {
+                        let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                        if let Some(m) = LoadoutMetric::ALL.iter().find(|m| m.as_str() == val) {
+                            metric.set(*m);
+                        }
+                    })
+                }>
+                    { for LoadoutMetric::ALL.iter().map(|m| html! {
+                        <option value={m.as_str().to_string()} selected={*m == *metric}>{m.as_str()}</option>
+                    })}
+                </select>
+            </div>
+            <div class="input-field">
+                <label>{"Picks Kept per Slot: "}</label>
+                <input type="number" step="1" min="1" max="6" class="table-input" style="border: 1px solid var(--border-color);"
+                    value={per_slot.to_string()}
+                    oninput={
+                        let per_slot = per_slot.clone();
+                        Callback::from(move |e: InputEvent| {
+                            let val: usize = e.target_unchecked_into::<web_sys::HtmlInputElement>().value().parse().unwrap_or(3);
+                            per_slot.set(val.clamp(1, 6));
+                        })
+                    }
+                />
+                <button class="io-btn" onclick={on_search}>{"Search Gear"}</button>
+            </div>
+
+            { for results.1.iter().enumerate().map(|(i, loadout)| {
+                let gear = Equipment::SLOTS.iter()
+                    .map(|&slot| {
+                        let enh = loadout.equipment.slot(slot);
+                        let mark = if loadout.changed.contains(&slot) { "*" } else { "" };
+                        format!("{}{mark}: {} / {}", slot_name(slot), enh.pattern.ratios().name, enh.r#trait.as_str())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let stats = loadout.stat_diff.iter()
+                    .map(|(key, diff)| format!("{key} {}", signed(*diff)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                html! {
+                    <div class="loadout-result">
+                        <div class="stat-row">
+                            <span class="label">{format!("#{} {}", i + 1, results.0.as_str())}</span>
+                            <span class="value">
+                                {format!("{:.1} ({}), DPS {:.1} ({})", loadout.value, signed(loadout.value_diff), loadout.dps, signed(loadout.dps_diff))}
+                            </span>
+                        </div>
+                        <div class="loadout-gear">{gear}</div>
+                        { if loadout.changed.is_empty() {
+                            html! { <div class="loadout-stats">{"Current gear"}</div> }
+                        } else {
+                            html! { <div class="loadout-stats">{stats}</div> }
+                        }}
+                        <button class="mini-delete-btn" onclick={
+                            let settings = props.settings.clone();
+                            let on_apply = props.on_apply.clone();
+                            let loadout = loadout.clone();
+                            Callback::from(move |_| {
+                                let mut build = settings.clone();
+                                loadout.apply(&mut build);
+                                on_apply.emit(build);
+                            })
+                        }>{"APPLY"}</button>
+                    </div>
+                }
+            })}
+        </div>
+    }
+}
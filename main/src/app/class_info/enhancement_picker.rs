@@ -1,7 +1,7 @@
 use yew::prelude::*;
 use backend::gear::{Enhancement, EnhancementPattern, Trait};
 use backend::damage::Skill;
-use crate::app::class_info::passive::{CustomPassive, OperationType, TargetType};
+use backend::passive::CustomPassive;
 
 
 #[derive(Properties, PartialEq)]
@@ -13,111 +13,6 @@ pub struct PickerProps {
     pub on_update_skills: Callback<Vec<(Skill, Vec<CustomPassive>, bool)>>,
 }
 
-fn get_passives_for_trait(tra: Trait) -> Vec<CustomPassive> {
-    match tra {
-        Trait::Clairvoyance => vec![
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "Hit Chance".to_string(),
-                value: 10.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            }
-        ],
-        Trait::Vainglory => vec![
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "All Out".to_string(),
-                value: 15.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            },
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "Heal In".to_string(),
-                value: -50.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            }
-        ],
-        Trait::Lament => vec![
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "Crit Chance".to_string(),
-                value: 20.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            },
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "Haste".to_string(),
-                value: -5.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            },
-        ],
-        Trait::Avarice => vec![
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "Haste".to_string(),
-                value: 10.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            },
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "All In".to_string(),
-                value: -35.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            }
-        ],
-        Trait::Absolution => vec![
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "Heal Out".to_string(),
-                value: 50.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            },
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "Phy Out".to_string(),
-                value: -20.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            },
-        ],
-        Trait::Penitence => vec![
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "All In".to_string(),
-                value: 25.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            },
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "DoT Out".to_string(),
-                value: -25.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            },
-        ],
-        Trait::Ether => vec![
-            CustomPassive {
-                target_type: TargetType::Secondary,
-                stat_name: "Mana Consumption".to_string(),
-                value: -33.0,
-                operation_type: OperationType::Additive,
-                ..Default::default()
-            },
-        ],
-        
-        _ => vec![],
-    }
-}
-
 #[function_component(EnhancementPicker)]
 pub fn enhancement_picker(props: &PickerProps) -> Html {
     let on_input = {
@@ -131,7 +26,7 @@ pub fn enhancement_picker(props: &PickerProps) -> Html {
             if let Some(l) = lvl { new_val.level = l; }
             if let Some(r) = rar { new_val.rarity = r; }
             if let Some(p) = pat { new_val.pattern = p; }
-            if let Some(t) = tra { new_val.r#trait = t; let passives = get_passives_for_trait(t); props_on_update_passives.emit(passives); }
+            if let Some(t) = tra { new_val.r#trait = t; let passives = t.passives(); props_on_update_passives.emit(passives); }
             props_on_change.emit(new_val);
         })
     };
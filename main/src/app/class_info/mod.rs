@@ -1,6 +1,7 @@
 pub mod class;
 pub mod enemy;
 pub mod enhancement_picker;
+pub mod enhancement_optimizer;
 pub mod skills;
 pub mod stats;
 pub mod passive;
@@ -8,3 +9,5 @@ pub mod build_manager;
 pub mod dps;
 pub mod buffs;
 pub mod enemy_skill;
+pub mod chart;
+pub mod sweep;
@@ -1,58 +1,17 @@
-use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use yew::prelude::*;
-use crate::app::class_info::class::ClassSettings;
-
-#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
-pub enum TargetType {
-    Primary,
-    Secondary,
-    Enemy,
-}
-
-#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
-pub enum OperationType {
-    Multiplicative,
-    Additive,
-}
-
-#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
-pub struct CustomPassive {
-    pub target_type: TargetType,
-    pub stat_name: String,
-    pub value: f32,
-    pub operation_type: OperationType,
-    pub duration: Option<u32>,
-}
-
-impl Default for CustomPassive {
-    fn default() -> Self {
-        CustomPassive {
-            target_type: TargetType::Primary,
-            stat_name: "Strength".to_string(),
-            value: 0.0,
-            operation_type: OperationType::Additive,
-            duration: None
-        }
-    }
-}
+use backend::build::Build;
+use backend::{passive::{CustomPassive, OperationType, TargetType}, stat::StatKey};
 
 #[derive(Properties, PartialEq)]
 pub struct PassiveProps {
-    pub settings: ClassSettings,
+    pub settings: Build,
     pub on_update_passives: Callback<Vec<CustomPassive>>,
 }
 #[function_component(PassiveManager)]
 pub fn passive_manager(props: &PassiveProps) -> Html {
     let passives_state = &props.settings.passives;
 
-    let primary_options = vec!["Strength", "Intellect", "Endurance", "Dexterity", "Wisdom", "Luck"];
-    let secondary_options = vec![
-        "Haste", "Crit Chance", "Hit Chance", "Dodge Chance",
-        "All Out", "Phy Out", "Mag Out", "Heal Out",
-        "All In", "Phy In", "Mag In", "Heal In",
-        "DoT In", "DoT Out", "Mana Consumption",
-        "Attack Power", "Spell Power", "Crit Modifier"
-    ];
 
     let state_handle = passives_state.clone();
     let on_update_parent = props.on_update_passives.clone();
@@ -85,11 +44,7 @@ pub fn passive_manager(props: &PassiveProps) -> Html {
                     let current_passive = passive.clone();
                     let up_cb = update_at_index.clone();
 
-                    let stat_options = if current_passive.target_type == TargetType::Primary {
-                        &primary_options
-                    } else {
-                        &secondary_options
-                    };
+                    let stat_options = StatKey::options(&current_passive.target_type);
 
                     html! {
                         <tr key={i}>
@@ -104,11 +59,11 @@ pub fn passive_manager(props: &PassiveProps) -> Html {
                                         match input.value().as_str() {
                                             "Secondary" => {
                                                 p.target_type = TargetType::Secondary;
-                                                p.stat_name = "Haste".to_string();
+                                                p.stat_name = StatKey::Haste;
                                             }
                                             _ => {
                                                 p.target_type = TargetType::Primary;
-                                                p.stat_name = "Strength".to_string();
+                                                p.stat_name = StatKey::Strength;
                                             }
                                         }
                                         up.emit((i, p));
@@ -126,12 +81,12 @@ pub fn passive_manager(props: &PassiveProps) -> Html {
                                     Callback::from(move |e: Event| {
                                         let input = e.target_unchecked_into::<web_sys::HtmlInputElement>();
                                         let mut p = current_passive.clone();
-                                        p.stat_name = input.value();
+                                        if let Ok(key) = StatKey::from_str(&input.value()) { p.stat_name = key; }
                                         up.emit((i, p.clone()));
                                     })
                                 }>
                                     { for stat_options.iter().map(|opt| html! {
-                                        <option value={*opt} selected={&current_passive.stat_name == opt}>{opt}</option>
+                                        <option value={opt.to_string()} selected={current_passive.stat_name == *opt}>{opt.to_string()}</option>
                                     })}
                                 </select>
                             </td>
@@ -149,6 +104,9 @@ pub fn passive_manager(props: &PassiveProps) -> Html {
                                             "Additive" => {
                                                 p.operation_type = OperationType::Additive;
                                             },
+                                            "Final" => {
+                                                p.operation_type = OperationType::Final;
+                                            },
                                             _ => {
                                                 p.operation_type = OperationType::Multiplicative;
                                                 p.value = 1.0;
@@ -160,6 +118,7 @@ pub fn passive_manager(props: &PassiveProps) -> Html {
                                 }>
                                     <option value="Additive" selected={current_passive.operation_type == OperationType::Additive}>{"Additive"}</option>
                                     <option value="Multiplicative" selected={current_passive.operation_type == OperationType::Multiplicative}>{"Multiplicative"}</option>
+                                    <option value="Final" selected={current_passive.operation_type == OperationType::Final}>{"Final"}</option>
                                 </select>
                             </td>
 
@@ -1,36 +1,240 @@
 use std::str::FromStr;
 use yew::prelude::*;
-use crate::app::class_info::class::{ClassSettings, enemy_incoming_modifier};
-use crate::app::class_info::passive::{CustomPassive, TargetType, OperationType};
-use backend::damage::{DamageSource, Skill, Type, Target};
+use backend::build::Build;
+use backend::{passive::{CustomPassive, TargetType, OperationType}, stat::StatKey};
+use backend::damage::{DamageFormula, DamageSource, DotEffect, DotRefresh, HpBack, Skill, StackGain, StackingFormula, StackingFunction, Type, Target};
 
 #[derive(Properties, PartialEq)]
 pub struct SkillProps {
-    pub settings: ClassSettings,
+    pub settings: Build,
     pub on_update_skills: Callback<Vec<(Skill, Vec<CustomPassive>, bool)>>,
 }
 
 
+/// Editor row for a DoT or HoT attached to a skill. `per_tick` is what one tick does to its target.
+fn over_time_row(key: String, label: &str, dot: DotEffect, per_tick: f32, set: Callback<Option<DotEffect>>) -> Html {
+    html! {
+        <tr {key} class="skill-buff-row">
+            <td colspan="10">
+                <div class="buff-editor">
+                    <span class="buff-prefix">{label}</span>
+
+                    <input type="number" step="0.01" class="table-input buff-val-input" title="Multiplier per tick" value={dot.damage.to_string()} oninput={
+                        let set = set.clone();
+                        Callback::from(move |e: InputEvent| {
+                            if let Ok(val) = e.target_unchecked_into::<web_sys::HtmlInputElement>().value().parse::<f32>() {
+                                set.emit(Some(DotEffect { damage: val, ..dot }));
+                            }
+                        })
+                    } />
+
+                    <select onchange={
+                        let set = set.clone();
+                        Callback::from(move |e: Event| {
+                            let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                            if let Ok(dsrc) = DamageSource::from_str(&val) {
+                                set.emit(Some(DotEffect { dsrc, ..dot }));
+                            }
+                        })
+                    }>
+                        <option value="AP1" selected={dot.dsrc == DamageSource::AP1}>{"AP"}</option>
+                        <option value="SP1" selected={dot.dsrc == DamageSource::SP1}>{"SP"}</option>
+                        <option value="APSP1" selected={dot.dsrc == DamageSource::APSP1}>{"AP + SP"}</option>
+                        <option value="cHPm" selected={dot.dsrc == DamageSource::cHPm}>{"Max HP"}</option>
+                    </select>
+
+                    <input type="number" step="100" class="table-input buff-val-input" title="Tick interval (ms)" value={dot.tick_interval.to_string()} oninput={
+                        let set = set.clone();
+                        Callback::from(move |e: InputEvent| {
+                            if let Ok(val) = e.target_unchecked_into::<web_sys::HtmlInputElement>().value().parse::<u32>() {
+                                set.emit(Some(DotEffect { tick_interval: val, ..dot }));
+                            }
+                        })
+                    } />
+
+                    <input type="number" step="100" class="table-input buff-val-input" title="Duration (ms)" value={dot.duration.to_string()} oninput={
+                        let set = set.clone();
+                        Callback::from(move |e: InputEvent| {
+                            if let Ok(val) = e.target_unchecked_into::<web_sys::HtmlInputElement>().value().parse::<u32>() {
+                                set.emit(Some(DotEffect { duration: val, ..dot }));
+                            }
+                        })
+                    } />
+
+                    <input type="number" step="1" min="1" class="table-input buff-val-input" title="Max stacks" value={dot.max_stacks.to_string()} oninput={
+                        let set = set.clone();
+                        Callback::from(move |e: InputEvent| {
+                            if let Ok(val) = e.target_unchecked_into::<web_sys::HtmlInputElement>().value().parse::<u32>() {
+                                set.emit(Some(DotEffect { max_stacks: val.max(1), ..dot }));
+                            }
+                        })
+                    } />
+
+                    <select onchange={
+                        let set = set.clone();
+                        Callback::from(move |e: Event| {
+                            let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                            if let Ok(refresh) = DotRefresh::from_str(&val) {
+                                set.emit(Some(DotEffect { refresh, ..dot }));
+                            }
+                        })
+                    }>
+                        <option value="Refresh" selected={dot.refresh == DotRefresh::Refresh}>{"Refresh"}</option>
+                        <option value="Extend" selected={dot.refresh == DotRefresh::Extend}>{"Extend"}</option>
+                        <option value="Keep" selected={dot.refresh == DotRefresh::Keep}>{"Keep"}</option>
+                    </select>
+
+                    <span class="dmg-cell">{ format!("{:.1} x {}", per_tick, dot.ticks()) }</span>
+
+                    <button class="mini-delete-btn" onclick={Callback::from(move |_| set.emit(None))}>{"DEL"}</button>
+                </div>
+            </td>
+        </tr>
+    }
+}
+
+/// Editor row for a skill's lifesteal, either a percent of damage dealt or a damage source amount.
+fn lifesteal_row(key: String, hp_back: HpBack, set: Callback<Option<HpBack>>) -> Html {
+    let mode = match hp_back {
+        HpBack::Percent(_) => "Percent".to_string(),
+        HpBack::Source(src) => format!("{:?}", src),
+    };
+
+    html! {
+        <tr {key} class="skill-buff-row">
+            <td colspan="10">
+                <div class="buff-editor">
+                    <span class="buff-prefix">{"↳ Lifesteal"}</span>
+
+                    <select onchange={
+                        let set = set.clone();
+                        Callback::from(move |e: Event| {
+                            let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                            if let Ok(src) = DamageSource::from_str(&val) {
+                                set.emit(Some(HpBack::Source(src)));
+                            } else {
+                                set.emit(Some(HpBack::Percent(10.0)));
+                            }
+                        })
+                    }>
+                        <option value="Percent" selected={mode == "Percent"}>{"% of Damage"}</option>
+                        <option value="AP1" selected={mode == "AP1"}>{"AP"}</option>
+                        <option value="SP1" selected={mode == "SP1"}>{"SP"}</option>
+                        <option value="cHPm" selected={mode == "cHPm"}>{"Max HP"}</option>
+                    </select>
+
+                    { if let HpBack::Percent(p) = hp_back {
+                        let set = set.clone();
+                        html! {
+                            <input type="number" step="0.5" class="table-input buff-val-input" title="Percent of damage dealt" value={p.to_string()} oninput={
+                                Callback::from(move |e: InputEvent| {
+                                    if let Ok(val) = e.target_unchecked_into::<web_sys::HtmlInputElement>().value().parse::<f32>() {
+                                        set.emit(Some(HpBack::Percent(val)));
+                                    }
+                                })
+                            } />
+                        }
+                    } else { html! {} }}
+
+                    <button class="mini-delete-btn" onclick={Callback::from(move |_| set.emit(None))}>{"DEL"}</button>
+                </div>
+            </td>
+        </tr>
+    }
+}
+
+/// Editor row for a stacking skill. Every edit rebuilds the function through its constructor.
+fn stacking_row(key: String, stacking: StackingFunction, set: Callback<Option<StackingFunction>>) -> Html {
+    let rebuild = move |limit: u32, formula: StackingFormula, gain: StackGain, decay: Option<u32>, consume: bool, stacks: u32| {
+        StackingFunction::new(limit, formula, gain, decay, consume).with_stacks(stacks)
+    };
+    let (limit, formula, gain, decay, consume, stacks) =
+        (stacking.stack_limit(), stacking.formula(), stacking.gain(), stacking.decay(), stacking.consume(), stacking.stacks());
+
+    html! {
+        <tr {key} class="skill-buff-row">
+            <td colspan="10">
+                <div class="buff-editor">
+                    <span class="buff-prefix">{"↳ Stacks"}</span>
+
+                    <input type="number" step="1" min="0" class="table-input buff-val-input" title="Current stacks" value={stacks.to_string()} oninput={
+                        let set = set.clone();
+                        Callback::from(move |e: InputEvent| {
+                            if let Ok(val) = e.target_unchecked_into::<web_sys::HtmlInputElement>().value().parse::<u32>() {
+                                set.emit(Some(rebuild(limit, formula, gain, decay, consume, val)));
+                            }
+                        })
+                    } />
+                    <span>{"/"}</span>
+                    <input type="number" step="1" min="1" class="table-input buff-val-input" title="Stack limit" value={limit.to_string()} oninput={
+                        let set = set.clone();
+                        Callback::from(move |e: InputEvent| {
+                            if let Ok(val) = e.target_unchecked_into::<web_sys::HtmlInputElement>().value().parse::<u32>() {
+                                set.emit(Some(rebuild(val, formula, gain, decay, consume, stacks)));
+                            }
+                        })
+                    } />
+
+                    <select title="Formula" onchange={
+                        let set = set.clone();
+                        Callback::from(move |e: Event| {
+                            let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                            if let Ok(f) = StackingFormula::from_str(&val) {
+                                set.emit(Some(rebuild(limit, f, gain, decay, consume, stacks)));
+                            }
+                        })
+                    }>
+                        <option value="Normal" selected={formula == StackingFormula::Normal}>{"1 + stacks/2"}</option>
+                        <option value="Weird" selected={formula == StackingFormula::Weird}>{"stacks/2"}</option>
+                    </select>
+
+                    <select title="Gained" onchange={
+                        let set = set.clone();
+                        Callback::from(move |e: Event| {
+                            let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                            if let Ok(g) = StackGain::from_str(&val) {
+                                set.emit(Some(rebuild(limit, formula, g, decay, consume, stacks)));
+                            }
+                        })
+                    }>
+                        <option value="PerCast" selected={gain == StackGain::PerCast}>{"Per Cast"}</option>
+                        <option value="PerHit" selected={gain == StackGain::PerHit}>{"Per Hit"}</option>
+                    </select>
+
+                    <input type="number" step="100" min="0" class="table-input buff-val-input" title="Decay (ms), 0 never decays" value={decay.unwrap_or(0).to_string()} oninput={
+                        let set = set.clone();
+                        Callback::from(move |e: InputEvent| {
+                            if let Ok(val) = e.target_unchecked_into::<web_sys::HtmlInputElement>().value().parse::<u32>() {
+                                let decay = if val == 0 { None } else { Some(val) };
+                                set.emit(Some(rebuild(limit, formula, gain, decay, consume, stacks)));
+                            }
+                        })
+                    } />
+
+                    <label title="Casting the skill spends its stacks">
+                        <input type="checkbox" checked={consume} onclick={
+                            let set = set.clone();
+                            Callback::from(move |_| set.emit(Some(rebuild(limit, formula, gain, decay, !consume, stacks))))
+                        } />
+                        {"Consume"}
+                    </label>
+
+                    <span class="dmg-cell">{ format!("x{:.2}", stacking.modifier()) }</span>
+
+                    <button class="mini-delete-btn" onclick={Callback::from(move |_| set.emit(None))}>{"DEL"}</button>
+                </div>
+            </td>
+        </tr>
+    }
+}
+
 #[function_component(Skills)]
 pub fn skills(props: &SkillProps) -> Html {
     let settings = &props.settings;
     let on_update_parent = props.on_update_skills.clone();
     let skills_list = &settings.skills;
+    let stat_layers = settings.stat_layers(&[]);
 
-    let primary_options = vec!["Strength", "Intellect", "Endurance", "Dexterity", "Wisdom", "Luck"];
-    let secondary_options = vec![
-        "Haste", "Crit Chance", "Hit Chance", "Dodge Chance",
-        "All Out", "Phy Out", "Mag Out", "Heal Out",
-        "All In", "Phy In", "Mag In", "Heal In",
-        "DoT In", "DoT Out", "Mana Consumption",
-        "Attack Power", "Spell Power", "Crit Modifier"
-    ];
-    // Stat names that map to EnemySecondaryStats fields via calculate_enemy_changes
-    let enemy_options = vec![
-        "All In", "Phy In", "Mag In", "DoT In", "Heal In",
-        "All Out", "Phy Out", "Mag Out", "DoT Out", "Heal Out",
-        "Crit Chance", "Crit Modifier", "Haste", "Dodge",
-    ];
 
     let update_skill_at = {
         let skills_list = skills_list.clone();
@@ -57,6 +261,7 @@ pub fn skills(props: &SkillProps) -> Html {
                             <th>{"Function"}</th>
                             <th>{"Type"}</th>
                             <th>{"Target"}</th>
+                            <th>{"Formula"}</th>
                             <th>{"CD (ms)"}</th>
                             <th>{"MP"}</th>
                             <th>{"Crit?"}</th>
@@ -71,8 +276,40 @@ pub fn skills(props: &SkillProps) -> Html {
                             let is_crit = *is_crit;
                             let up_cb = update_skill_at.clone();
 
-                            let enemy_mod = enemy_incoming_modifier(&skill.damage_type, &settings.enemy);
-                            let res = skill.compute(&settings.weapon, &settings.secondary_stats, is_crit) * enemy_mod;
+                            // Damage goes through the enemy's In modifiers, heals through the caster's Heal In
+                            let breakdown = skill.breakdown(&settings.weapon, &stat_layers, is_crit, Some(&settings.enemy));
+                            let res = breakdown.final_value;
+
+                            let set_hp_back = {
+                                let p = passives.clone();
+                                let up = up_cb.clone();
+                                Callback::from(move |hp_back: Option<HpBack>| {
+                                    let mut s = skill;
+                                    s.properties.hp_back = hp_back;
+                                    up.emit((i, s, p.clone(), is_crit));
+                                })
+                            };
+
+                            let set_stacking = {
+                                let p = passives.clone();
+                                let up = up_cb.clone();
+                                Callback::from(move |stacking: Option<StackingFunction>| {
+                                    let mut s = skill;
+                                    s.properties.stacking = stacking;
+                                    up.emit((i, s, p.clone(), is_crit));
+                                })
+                            };
+
+                            // Replaces (or removes) this skill's DoT, or its HoT when the flag is set
+                            let set_over_time = {
+                                let p = passives.clone();
+                                let up = up_cb.clone();
+                                Callback::from(move |(effect, hot): (Option<DotEffect>, bool)| {
+                                    let mut s = skill;
+                                    if hot { s.properties.hot = effect; } else { s.properties.dot = effect; }
+                                    up.emit((i, s, p.clone(), is_crit));
+                                })
+                            };
 
                             html! {
                                 <>
@@ -136,6 +373,7 @@ pub fn skills(props: &SkillProps) -> Html {
                                                 <option value="Magical" selected={skill.damage_type == Type::Magical}>{"Magical"}</option>
                                                 <option value="TrueDamage" selected={skill.damage_type == Type::TrueDamage}>{"True"}</option>
                                                 <option value="DamageOverTime" selected={skill.damage_type == Type::DamageOverTime}>{"DoT"}</option>
+                                                <option value="Heal" selected={skill.damage_type == Type::Heal}>{"Heal"}</option>
                                             </select>
                                         </td>
                                         <td>
@@ -157,6 +395,24 @@ pub fn skills(props: &SkillProps) -> Html {
                                                 <option value="Friendly" selected={skill.target == Target::Friendly}>{"Friendly"}</option>
                                             </select>
                                         </td>
+                                        <td>
+                                            <select onchange={
+                                                let s = skill;
+                                                let p = passives.clone();
+                                                let up = up_cb.clone();
+                                                Callback::from(move |e: Event| {
+                                                    let mut s = s;
+                                                    let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                                    if let Ok(f) = DamageFormula::from_str(&val) {
+                                                        s.formula = f;
+                                                        up.emit((i, s, p.clone(), is_crit));
+                                                    }
+                                                })
+                                            }>
+                                                <option value="Standard" selected={skill.formula == DamageFormula::Standard}>{"Standard"}</option>
+                                                <option value="Chrono2" selected={skill.formula == DamageFormula::Chrono2}>{"Chrono2"}</option>
+                                            </select>
+                                        </td>
                                         <td>
                                             <input type="number" class="table-input" value={skill.cd.to_string()}
                                                 oninput={
@@ -198,7 +454,7 @@ pub fn skills(props: &SkillProps) -> Html {
                                                 }
                                             />
                                         </td>
-                                        <td class={if is_crit { "dmg-cell crit" } else { "dmg-cell" }}>
+                                        <td class={if is_crit { "dmg-cell crit" } else { "dmg-cell" }} title={breakdown.to_string()}>
                                             { format!("{:.1}", res) }
                                         </td>
                                         <td>
@@ -212,22 +468,69 @@ pub fn skills(props: &SkillProps) -> Html {
                                                     up.emit((i, s, p.clone(), is_crit));
                                                 })
                                             }>{"[+] Effect"}</button>
+                                            { if skill.properties.dot.is_none() {
+                                                html! {
+                                                    <button class="add-buff-mini-btn" onclick={
+                                                        let set = set_over_time.clone();
+                                                        Callback::from(move |_| set.emit((Some(DotEffect::default()), false)))
+                                                    }>{"[+] DoT"}</button>
+                                                }
+                                            } else { html! {} }}
+                                            { if skill.properties.stacking.is_none() {
+                                                html! {
+                                                    <button class="add-buff-mini-btn" onclick={
+                                                        let set = set_stacking.clone();
+                                                        Callback::from(move |_| set.emit(Some(StackingFunction::new(5, StackingFormula::Normal, StackGain::PerCast, None, false))))
+                                                    }>{"[+] Stacks"}</button>
+                                                }
+                                            } else { html! {} }}
+                                            { if skill.properties.hp_back.is_none() {
+                                                html! {
+                                                    <button class="add-buff-mini-btn" onclick={
+                                                        let set = set_hp_back.clone();
+                                                        Callback::from(move |_| set.emit(Some(HpBack::Percent(10.0))))
+                                                    }>{"[+] Lifesteal"}</button>
+                                                }
+                                            } else { html! {} }}
+                                            { if skill.properties.hot.is_none() {
+                                                html! {
+                                                    <button class="add-buff-mini-btn" onclick={
+                                                        let set = set_over_time.clone();
+                                                        Callback::from(move |_| set.emit((Some(DotEffect::default()), true)))
+                                                    }>{"[+] HoT"}</button>
+                                                }
+                                            } else { html! {} }}
                                         </td>
                                     </tr>
 
+                                    { if let Some(hp_back) = skill.properties.hp_back {
+                                        lifesteal_row(format!("skill-{}-lifesteal", i), hp_back, set_hp_back.clone())
+                                    } else { html! {} }}
+                                    { if let Some(stacking) = skill.properties.stacking {
+                                        stacking_row(format!("skill-{}-stacks", i), stacking, set_stacking.clone())
+                                    } else { html! {} }}
+                                    { if let Some(dot) = skill.properties.dot {
+                                        let tick = dot.snapshot(&skill, &settings.weapon, &stat_layers)
+                                            * settings.enemy.incoming_modifier(&Type::DamageOverTime);
+                                        let set = set_over_time.reform(move |dot| (dot, false));
+                                        over_time_row(format!("skill-{}-dot", i), "↳ DoT", dot, tick, set)
+                                    } else { html! {} }}
+                                    { if let Some(hot) = skill.properties.hot {
+                                        let tick = hot.heal_snapshot(&skill, &settings.weapon, &stat_layers)
+                                            * (settings.secondary_stats.heal_in / 100.0);
+                                        let set = set_over_time.reform(move |hot| (hot, true));
+                                        over_time_row(format!("skill-{}-hot", i), "↳ HoT", hot, tick, set)
+                                    } else { html! {} }}
+
 
                                     { for passives.iter().enumerate().map(|(p_idx, current_passive)| {
                                         let current_passive = current_passive.clone();
-                                        let stat_options = match current_passive.target_type {
-                                            TargetType::Primary   => &primary_options,
-                                            TargetType::Secondary => &secondary_options,
-                                            TargetType::Enemy     => &enemy_options,
-                                        };
+                                        let stat_options = StatKey::options(&current_passive.target_type);
                                         let is_debuff = current_passive.target_type == TargetType::Enemy;
 
                                         html! {
                                             <tr key={format!("skill-{}-buff-{}", i, p_idx)} class="skill-buff-row">
-                                                <td colspan="9">
+                                                <td colspan="10">
                                                     <div class="buff-editor">
                                                         <span class="buff-prefix" style={if is_debuff { "color: #f85149;" } else { "" }}>
                                                             { if is_debuff { format!("↳ Debuff #{}", p_idx + 1) } else { format!("↳ Buff #{}", p_idx + 1) } }
@@ -245,15 +548,15 @@ pub fn skills(props: &SkillProps) -> Html {
                                                                     match val.as_str() {
                                                                         "Secondary" => {
                                                                             p.target_type = TargetType::Secondary;
-                                                                            p.stat_name = "Haste".to_string();
+                                                                            p.stat_name = StatKey::Haste;
                                                                         },
                                                                         "Enemy" => {
                                                                             p.target_type = TargetType::Enemy;
-                                                                            p.stat_name = "All In".to_string();
+                                                                            p.stat_name = StatKey::AllIn;
                                                                         },
                                                                         _ => {
                                                                             p.target_type = TargetType::Primary;
-                                                                            p.stat_name = "Strength".to_string();
+                                                                            p.stat_name = StatKey::Strength;
                                                                         }
                                                                     }
                                                                     up.emit((i, s, p_list.clone(), is_crit));
@@ -273,13 +576,13 @@ pub fn skills(props: &SkillProps) -> Html {
                                                             Callback::from(move |e: Event| {
                                                                 let mut p_list = p_list.clone();
                                                                 if let Some(p) = p_list.get_mut(p_idx) {
-                                                                    p.stat_name = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                                                    if let Ok(key) = StatKey::from_str(&e.target_unchecked_into::<web_sys::HtmlInputElement>().value()) { p.stat_name = key; }
                                                                     up.emit((i, s, p_list.clone(), is_crit));
                                                                 }
                                                             })
                                                         }>
                                                             { for stat_options.iter().map(|opt| html! {
-                                                                <option value={*opt} selected={&current_passive.stat_name == opt}>{opt}</option>
+                                                                <option value={opt.to_string()} selected={current_passive.stat_name == *opt}>{opt.to_string()}</option>
                                                             })}
                                                         </select>
 
@@ -294,6 +597,7 @@ pub fn skills(props: &SkillProps) -> Html {
                                                                     let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
                                                                     p.operation_type = match val.as_str() {
                                                                         "Multiplicative" => OperationType::Multiplicative,
+                                                                        "Final" => OperationType::Final,
                                                                         _ => OperationType::Additive,
                                                                     };
                                                                     up.emit((i, s, p_list.clone(), is_crit));
@@ -302,6 +606,7 @@ pub fn skills(props: &SkillProps) -> Html {
                                                         }>
                                                             <option value="Additive" selected={current_passive.operation_type == OperationType::Additive}>{"Add"}</option>
                                                             <option value="Multiplicative" selected={current_passive.operation_type == OperationType::Multiplicative}>{"Mult"}</option>
+                                                            <option value="Final" selected={current_passive.operation_type == OperationType::Final}>{"Final"}</option>
                                                         </select>
 
                                                         <input type="number" step="0.01" class="table-input buff-val-input" value={current_passive.value.to_string()} oninput={
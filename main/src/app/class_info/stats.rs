@@ -1,15 +1,67 @@
 use yew::prelude::*;
-use crate::app::class_info::class::ClassSettings;
+use backend::build::Build;
+use backend::sim::{FightSettings, RotationAction};
+use backend::stat::{StatDerivation, StatKey};
+use backend::weights::{StatWeight, stat_weights};
 
 #[derive(Properties, PartialEq)]
 pub struct StatProps {
-    pub settings: ClassSettings,
+    pub settings: Build,
+    /// The DPS panel's fight and rotation, the weights are measured in the same fight
+    pub fight: FightSettings,
+    pub rotation: Vec<RotationAction>,
+}
+
+// Stats changed by a passive show how they got there underneath,
+// and stats over a cap show the value the fight actually uses next to it
+fn stat_row(label: &str, value: String, capped: Option<String>, derivation: Option<StatDerivation>, weight: Option<StatWeight>) -> Html {
+    html! {
+        <>
+            <div class="stat-row">
+                <span class="label">{label}</span>
+                { match weight {
+                    Some(w) => html! {
+                        <span class="stat-weight" title="Per extra point: DPS / effective HP">
+                            {format!("{:+.2} DPS / {:+.0} EHP", w.dps, w.ehp)}
+                        </span>
+                    },
+                    None => html! {},
+                }}
+                { match capped {
+                    Some(c) => html! { <span class="capped-value">{format!("→ {c}")}</span> },
+                    None => html! {},
+                }}
+                <span class="value">{value}</span>
+            </div>
+            { match derivation {
+                Some(d) => html! { <div class="stat-derivation">{d.to_string()}</div> },
+                None => html! {},
+            }}
+        </>
+    }
 }
 
 #[function_component(StatDisplay)]
 pub fn stat_display(props: &StatProps) -> Html {
     let p = &props.settings.primary_stats;
     let s = &props.settings.secondary_stats;
+    let (primary_steps, secondary_steps) = props.settings.derivations();
+    let find = |steps: &[StatDerivation], label: &str| -> Option<StatDerivation> {
+        let key: StatKey = label.parse().ok()?;
+        steps.iter().find(|d| d.key == key && d.is_modified()).cloned()
+    };
+
+    // Weighing takes a fight per stat, so it only runs when asked and again when the build,
+    // the DPS panel's fight or its rotation changes.
+    let show_weights = use_state(|| false);
+    let weights = use_memo(
+        (props.settings.clone(), props.fight.clone(), props.rotation.clone(), *show_weights),
+        |(build, fight, rotation, show)| show.then(|| stat_weights(build, fight, rotation)),
+    );
+    let weight = |label: &str| -> Option<StatWeight> {
+        let key: StatKey = label.parse().ok()?;
+        weights.as_ref().as_ref()?.iter().find(|w| w.key == key).copied()
+    };
 
     let primaries = vec![
         ("Strength", p.strength),
@@ -22,7 +74,7 @@ pub fn stat_display(props: &StatProps) -> Html {
 
     let secondaries = vec![
         ("Hit Points", s.hp as f32),
-        ("Mana", 100.0),
+        ("Mana", s.mp as f32),
         ("Haste", s.haste),
         ("Crit Chance", s.crit_chance),
         ("Hit Chance", s.hit_chance),
@@ -45,23 +97,30 @@ pub fn stat_display(props: &StatProps) -> Html {
 
     html! {
         <div class="stats-screen">
+            <div class="input-field">
+                <label>{"Show Stat Weights: "}</label>
+                <input type="checkbox" checked={*show_weights}
+                    onclick={
+                        let show_weights = show_weights.clone();
+                        Callback::from(move |_| show_weights.set(!*show_weights))
+                    }
+                />
+            </div>
             <div class="stat-block">
                 <h4>{"Primary"}</h4>
-                { for primaries.into_iter().map(|(label, val)| html! {
-                    <div class="stat-row">
-                        <span class="label">{label}</span>
-                        <span class="value">{val}</span>
-                    </div>
+                { for primaries.into_iter().map(|(label, val)| {
+                    stat_row(label, val.to_string(), None, find(&primary_steps, label), weight(label))
                 })}
             </div>
 
             <div class="stat-block">
                 <h4>{"Secondary"}</h4>
-                { for secondaries.into_iter().map(|(label, val)| html! {
-                    <div class="stat-row">
-                        <span class="label">{label}</span>
-                        <span class="value">{ format!("{:.2}%", val) }</span>
-                    </div>
+                { for secondaries.into_iter().map(|(label, val)| {
+                    let capped = label.parse::<StatKey>().ok()
+                        .map(|key| key.clamp(val))
+                        .filter(|c| *c != val)
+                        .map(|c| format!("{:.2}%", c));
+                    stat_row(label, format!("{:.2}%", val), capped, find(&secondary_steps, label), weight(label))
                 })}
             </div>
         </div>
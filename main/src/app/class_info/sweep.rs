@@ -0,0 +1,137 @@
+use yew::prelude::*;
+use backend::build::Build;
+use backend::sim::{FightSettings, RotationAction};
+use backend::stat::StatKey;
+use backend::sweep::{Sweep, SweepAxis, SweepParam, sweep};
+use crate::app::class_info::chart::{Heatmap, LineChart, Series};
+
+#[derive(Properties, PartialEq)]
+pub struct SweepProps {
+    pub settings: Build,
+    pub fight: FightSettings,
+    pub rotation: Vec<RotationAction>,
+}
+
+/// A range that starts at the build's own value and goes a sensible way up from it, kept within the caps.
+fn default_axis(param: SweepParam, build: &Build) -> SweepAxis {
+    let span = match param {
+        SweepParam::WeaponBoost => 1.0,
+        SweepParam::WeaponDps => 50.0,
+        _ => 10.0,
+    };
+    let within = |value: f32| param.limit().map_or(value, |limit| limit.clamp(value));
+    let from = param.current(build);
+    SweepAxis { param, from: within(from), to: within(from + span), steps: 11 }
+}
+
+/// Parameter dropdown and from / to / steps inputs for one axis.
+fn axis_editor(label: &str, axis: &UseStateHandle<SweepAxis>, build: &Build) -> Html {
+    let on_param = {
+        let axis = axis.clone();
+        let build = build.clone();
+        Callback::from(move |e: Event| {
+            let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+            if let Some(param) = SweepParam::options().into_iter().find(|p| p.to_string() == val) {
+                axis.set(SweepAxis { steps: axis.steps, ..default_axis(param, &build) });
+            }
+        })
+    };
+    let number_cb = |set: fn(&mut SweepAxis, f32)| {
+        let axis = axis.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Ok(val) = e.target_unchecked_into::<web_sys::HtmlInputElement>().value().parse::<f32>() {
+                let mut next = *axis;
+                set(&mut next, val);
+                axis.set(next);
+            }
+        })
+    };
+
+    html! {
+        <div class="input-field sweep-axis">
+            <label>{label}</label>
+            <select onchange={on_param}>
+                { for SweepParam::options().into_iter().map(|p| html! {
+                    <option value={p.to_string()} selected={p == axis.param}>{p.to_string()}</option>
+                })}
+            </select>
+            <input type="number" step="any" class="table-input" title="From" value={axis.from.to_string()}
+                oninput={number_cb(|a, v| a.from = v)} />
+            <input type="number" step="any" class="table-input" title="To" value={axis.to.to_string()}
+                oninput={number_cb(|a, v| a.to = v)} />
+            <input type="number" step="1" min="1" class="table-input" title="Steps" value={axis.steps.to_string()}
+                oninput={number_cb(|a, v| a.steps = v.max(1.0) as usize)} />
+        </div>
+    }
+}
+
+#[function_component(SweepPanel)]
+pub fn sweep_panel(props: &SweepProps) -> Html {
+    let x_axis = use_state(|| default_axis(SweepParam::WeaponDps, &props.settings));
+    let use_y = use_state(|| false);
+    let y_axis = use_state(|| SweepAxis { steps: 5, ..default_axis(SweepParam::Secondary(StatKey::Haste), &props.settings) });
+    let result = use_state(|| None::<Result<Sweep, String>>);
+
+    let on_run = {
+        let settings = props.settings.clone();
+        let fight = props.fight.clone();
+        let rotation = props.rotation.clone();
+        let (x_axis, y_axis, use_y, result) = (x_axis.clone(), y_axis.clone(), use_y.clone(), result.clone());
+        Callback::from(move |_| {
+            let y = (*use_y).then_some(*y_axis);
+            result.set(Some(sweep(&settings, &fight, &rotation, *x_axis, y).map_err(|e| e.to_string())));
+        })
+    };
+
+    let output = match &*result {
+        None => html! {},
+        Some(Err(e)) => html! { <div class="priority-error">{e}</div> },
+        Some(Ok(sweep)) => {
+            let csv_href = format!("data:text/csv;charset=utf-8,{}", String::from(js_sys::encode_uri_component(&sweep.to_csv())));
+            let chart = match &sweep.y {
+                None => html! {
+                    <LineChart
+                        series={vec![Series { label: "DPS".to_string(), points: sweep.points.iter().map(|p| (p.x, p.dps)).collect() }]}
+                        x_label={sweep.x.param.to_string()}
+                    />
+                },
+                Some(y) => {
+                    // Points come row by row, one row per y value
+                    let xs = sweep.x.values();
+                    let values: Vec<Vec<f32>> = sweep.points.chunks(xs.len()).map(|row| row.iter().map(|p| p.dps).collect()).collect();
+                    html! {
+                        <Heatmap xs={xs} ys={y.values()} values={values}
+                            x_label={sweep.x.param.to_string()} y_label={y.param.to_string()} />
+                    }
+                }
+            };
+            html! {
+                <>
+                    <span class="label">{"DPS"}</span>
+                    { chart }
+                    <a class="io-btn" href={csv_href} download="sweep.csv">{"Export CSV"}</a>
+                </>
+            }
+        }
+    };
+
+    html! {
+        <div class="stat-block sweep-panel">
+            <h4>{"Sensitivity Sweep"}</h4>
+            <div style="font-size: var(--fs-xs); color: var(--text-muted); margin-bottom: 6px;">{"Parameter, from, to, steps"}</div>
+            { axis_editor("X: ", &x_axis, &props.settings) }
+            <div class="input-field">
+                <label>{"Second Parameter: "}</label>
+                <input type="checkbox" checked={*use_y}
+                    onclick={
+                        let use_y = use_y.clone();
+                        Callback::from(move |_| use_y.set(!*use_y))
+                    }
+                />
+            </div>
+            { if *use_y { axis_editor("Y: ", &y_axis, &props.settings) } else { html! {} } }
+            <button class="io-btn" onclick={on_run}>{"Run Sweep"}</button>
+            { output }
+        </div>
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 use js_sys::{Object, Reflect, Function, Promise};
-use crate::app::class_info::class::ClassSettings;
+use backend::build::{Build, load_builds};
 
 const STORAGE_KEY: &str = "dex_builds";
 
@@ -32,15 +32,15 @@ fn tauri_call(invoke_fn: &Function, cmd: &str, args: &JsValue) -> Option<Promise
 
 // ── localStorage fallbacks (used in browser / trunk serve) ──────────────────
 
-fn ls_load() -> HashMap<String, ClassSettings> {
+fn ls_load() -> HashMap<String, Build> {
     web_sys::window()
         .and_then(|w| w.local_storage().ok().flatten())
         .and_then(|s| s.get_item(STORAGE_KEY).ok().flatten())
-        .and_then(|json| serde_json::from_str(&json).ok())
+        .and_then(|json| load_builds(&json).ok())
         .unwrap_or_default()
 }
 
-fn ls_save(builds: &HashMap<String, ClassSettings>) {
+fn ls_save(builds: &HashMap<String, Build>) {
     if let Some(window) = web_sys::window() && let Ok(Some(storage)) = window.local_storage() &&
     let Ok(json) = serde_json::to_string(builds) {
         let _ = storage.set_item(STORAGE_KEY, &json); 
@@ -53,10 +53,10 @@ fn ls_save(builds: &HashMap<String, ClassSettings>) {
 ///
 /// In Tauri: reads `{app_data_dir}/builds.json` via the `load_builds` command.
 /// In browser: reads from `localStorage`.
-pub async fn load_all_builds() -> HashMap<String, ClassSettings> {
+pub async fn load_all_builds() -> HashMap<String, Build> {
     if let Some(invoke_fn) = tauri_invoke_fn() {
         let args = Object::new();
-        if let Some(promise) = tauri_call(&invoke_fn, "load_builds", &args.into()) && let Ok(result) = JsFuture::from(promise).await && let Some(json) = result.as_string() && let Ok(builds) = serde_json::from_str::<HashMap<String, ClassSettings>>(&json) {
+        if let Some(promise) = tauri_call(&invoke_fn, "load_builds", &args.into()) && let Ok(result) = JsFuture::from(promise).await && let Some(json) = result.as_string() && let Ok(builds) = load_builds(&json) {
             return builds;
         }
     }
@@ -68,7 +68,7 @@ pub async fn load_all_builds() -> HashMap<String, ClassSettings> {
 ///
 /// In Tauri: writes `{app_data_dir}/builds.json` via the `save_builds` command.
 /// In browser: writes to `localStorage`.
-pub async fn save_all_builds(builds: &HashMap<String, ClassSettings>) {
+pub async fn save_all_builds(builds: &HashMap<String, Build>) {
     if let Some(invoke_fn) = tauri_invoke_fn() && let Ok(json) = serde_json::to_string(builds) {
         // Tauri 2 converts camelCase JS arg names → snake_case Rust params.
         // buildsJson  →  builds_json: String